@@ -0,0 +1,12 @@
+# Source this from ~/.bashrc and run gravilycd instead of gravily, quitting
+# with Q then leaves the shell in the directory gravily was in
+gravilycd() {
+    local tmp dir
+    tmp="$(mktemp)" || return
+    command gravily --choosedir "$tmp" "$@"
+    dir="$(cat -- "$tmp")"
+    rm -f -- "$tmp"
+    if [ -n "$dir" ] && [ -d "$dir" ] && [ "$dir" != "$PWD" ]; then
+        cd -- "$dir" || return
+    fi
+}
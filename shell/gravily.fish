@@ -0,0 +1,11 @@
+# Save as ~/.config/fish/functions/gravilycd.fish and run gravilycd instead of
+# gravily, quitting with Q then leaves the shell in the directory gravily was in
+function gravilycd --wraps gravily --description 'Run gravily and cd where it quit with Q'
+    set -l tmp (mktemp); or return
+    command gravily --choosedir $tmp $argv
+    set -l dir (cat $tmp)
+    rm -f $tmp
+    if test -n "$dir" -a -d "$dir" -a "$dir" != "$PWD"
+        cd $dir
+    end
+end
@@ -0,0 +1,746 @@
+use super::FileManager;
+use crate::gravily::SortEntry;
+use crate::gravily::helper_functions::format_size;
+use crate::gravily::{
+    ArchiveContents, ArchiveEntry, ArchiveKind, ArchiveLoader, ArchiveRequest, ArchiveResult,
+    ArchiveView, ExtractRequest,
+};
+
+use flate2::read::GzDecoder;
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Rect},
+    style::Stylize,
+    text::Line,
+    widgets::{Block, List, Paragraph, Row, Table, Widget},
+};
+use tempfile::TempDir;
+use xz2::read::XzDecoder;
+use zip::ZipArchive;
+
+use std::collections::VecDeque;
+use std::ffi::OsString;
+#[cfg(unix)]
+use std::fs::Permissions;
+use std::fs::{File, create_dir, remove_file};
+use std::io::{self, BufReader, Read, Write};
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc::{Receiver, Sender, TryRecvError, channel};
+use std::thread;
+use std::time::SystemTime;
+
+// Entries bigger than this aren't extracted just to be previewed
+const MAX_PREVIEW_SIZE: u64 = 64 * 1024 * 1024;
+
+// Hovered archives stop being read after this many entries
+const MAX_LISTED_ENTRIES: usize = 1000;
+
+// Tables of contents of this many recently hovered archives are kept
+const LISTING_CACHE_SIZE: usize = 16;
+
+const ARCHIVE_EXTENSIONS: [(&str, ArchiveKind); 8] = [
+    (".zip", ArchiveKind::Zip),
+    (".tar", ArchiveKind::Tar),
+    (".tar.gz", ArchiveKind::TarGz),
+    (".tgz", ArchiveKind::TarGz),
+    (".tar.xz", ArchiveKind::TarXz),
+    (".txz", ArchiveKind::TarXz),
+    (".tar.zst", ArchiveKind::TarZst),
+    (".tzst", ArchiveKind::TarZst),
+];
+
+impl ArchiveKind {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let file_name = path.file_name()?.to_str()?.to_lowercase();
+
+        ARCHIVE_EXTENSIONS
+            .into_iter()
+            .find(|(extension, _)| file_name.ends_with(extension))
+            .map(|(_, kind)| kind)
+    }
+}
+
+// The archive's file name without its extension, "photos.tar.gz" -> "photos"
+pub fn archive_stem(path: &Path) -> Option<String> {
+    let file_name = path.file_name()?.to_str()?;
+
+    let (extension, _) = ARCHIVE_EXTENSIONS
+        .into_iter()
+        .find(|(extension, _)| file_name.to_lowercase().ends_with(extension))?;
+
+    file_name
+        .get(..file_name.len() - extension.len())
+        .filter(|stem| !stem.is_empty())
+        .map(str::to_owned)
+}
+
+impl FileManager {
+    pub fn is_in_archive(&self) -> bool {
+        self.archive.is_some()
+    }
+
+    pub fn enter_archive(&mut self, archive_path: PathBuf, kind: ArchiveKind) -> bool {
+        match read_entries(&archive_path, kind, usize::MAX) {
+            Ok(entries) => {
+                self.path = archive_path.clone();
+                self.archive = Some(ArchiveView {
+                    archive_path,
+                    kind,
+                    entries,
+                    inner_path: PathBuf::new(),
+                });
+                true
+            }
+            Err(e) => {
+                self.error = format!("Error opening archive {}: {}", archive_path.display(), e);
+                false
+            }
+        }
+    }
+
+    // Names of the entries directly inside `inner_path`, directories that only
+    // exist implicitly (as a prefix of other entries) are included
+    pub fn archive_children(&self, inner_path: &Path) -> Vec<(OsString, bool)> {
+        let Some(archive) = &self.archive else {
+            return Vec::new();
+        };
+
+        let mut children: Vec<(OsString, bool)> = Vec::new();
+
+        for entry in &archive.entries {
+            let Ok(relative_path) = entry.path.strip_prefix(inner_path) else {
+                continue;
+            };
+
+            let mut components = relative_path.components();
+            let Some(child) = components.next() else {
+                continue;
+            };
+
+            let is_dir = entry.is_dir || components.next().is_some();
+            let child = child.as_os_str().to_owned();
+
+            match children.iter_mut().find(|(name, _)| *name == child) {
+                Some((_, child_is_dir)) => *child_is_dir |= is_dir,
+                None => children.push((child, is_dir)),
+            }
+        }
+
+        children
+    }
+
+    pub fn read_archive_items(&mut self) {
+        if let Some(archive) = &self.archive {
+            let inner_path = archive.inner_path.clone();
+
+            let mut entries: Vec<SortEntry> = self
+                .archive_children(&inner_path)
+                .into_iter()
+                .map(|(name, is_dir)| SortEntry::from_name(name, is_dir))
+                .filter(|entry| self.is_shown(&entry.name, entry.is_dir))
+                .collect();
+
+            self.sort.sort(&mut entries);
+            self.path_items = entries.into_iter().map(|entry| entry.name).collect();
+        }
+    }
+
+    // Path of a hovered item relative to the root of the open archive
+    fn archive_inner_path(&self, path: &Path) -> Option<PathBuf> {
+        let archive = self.archive.as_ref()?;
+        path.strip_prefix(&archive.archive_path)
+            .ok()
+            .map(Path::to_path_buf)
+    }
+
+    pub fn is_archive_dir(&self, path: &Path) -> bool {
+        let Some(inner_path) = self.archive_inner_path(path) else {
+            return false;
+        };
+
+        let (Some(parent), Some(file_name)) = (inner_path.parent(), inner_path.file_name()) else {
+            return false;
+        };
+
+        self.archive_children(parent)
+            .iter()
+            .any(|(name, is_dir)| name == file_name && *is_dir)
+    }
+
+    // Opens the archive `path` lies in, or reuses it when it's already open,
+    // and the directory inside it that `path` points to
+    pub fn open_archive_location(&mut self, path: PathBuf) -> bool {
+        let Some((archive_path, kind)) = path.ancestors().find_map(|ancestor| {
+            let kind = ArchiveKind::from_path(ancestor).filter(|_| ancestor.is_file())?;
+            Some((ancestor.to_path_buf(), kind))
+        }) else {
+            self.error = format!("{} doesn't exist", path.display());
+            return false;
+        };
+
+        let already_open = self
+            .archive
+            .as_ref()
+            .is_some_and(|archive| archive.archive_path == archive_path);
+        if !already_open && !self.enter_archive(archive_path.clone(), kind) {
+            return false;
+        }
+
+        // falls back to the root of the archive
+        let path = if path == archive_path || self.is_archive_dir(&path) {
+            path
+        } else {
+            self.error = format!("{} isn't a directory in the archive", path.display());
+            archive_path.clone()
+        };
+
+        if let Some(archive) = &mut self.archive {
+            archive.inner_path = path
+                .strip_prefix(&archive_path)
+                .map(Path::to_path_buf)
+                .unwrap_or_default();
+            self.path = path;
+        }
+
+        true
+    }
+
+    // Where the regular previews can read a hovered item from. Files inside
+    // the open archive are extracted by the archive loader first, None means
+    // that's still going on. Paths outside of archives are returned as is.
+    pub fn resolve_preview_path(&mut self, path: &Path) -> Option<Result<PathBuf, String>> {
+        let (Some(archive), Some(inner_path)) = (&self.archive, self.archive_inner_path(path))
+        else {
+            return Some(Ok(path.to_path_buf()));
+        };
+
+        let Some(entry) = archive
+            .entries
+            .iter()
+            .find(|entry| entry.path == inner_path && !entry.is_dir)
+        else {
+            return Some(Err(String::from("Entry not found in archive")));
+        };
+
+        if entry.size > MAX_PREVIEW_SIZE {
+            return Some(Err(String::from("Too large to preview")));
+        }
+
+        let request = ExtractRequest {
+            path: path.to_path_buf(),
+            modified: archive
+                .archive_path
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .ok(),
+            archive_path: archive.archive_path.clone(),
+            kind: archive.kind,
+            entry: entry.clone(),
+        };
+
+        let Some(archive_loader) = &mut self.archive_loader else {
+            return Some(Err(String::from("Archive previews aren't available")));
+        };
+
+        match archive_loader.extracted(&request) {
+            Some(result) => Some(result.clone()),
+            None => {
+                archive_loader.request(ArchiveRequest::Extract(request));
+                None
+            }
+        }
+    }
+
+    pub fn render_archive_item(&mut self, path: &Path, block: Block, area: Rect, buf: &mut Buffer) {
+        if self.is_archive_dir(path) {
+            let inner_path = self.archive_inner_path(path).unwrap_or_default();
+
+            let items: Vec<String> = self
+                .archive_children(&inner_path)
+                .into_iter()
+                .map(|(name, _)| name.to_string_lossy().to_string())
+                .collect();
+
+            List::new(items).block(block).render(area, buf);
+            return;
+        }
+
+        match self.resolve_preview_path(path) {
+            Some(Ok(preview_path)) => self.render_file_preview(&preview_path, block, area, buf),
+            Some(Err(e)) => Paragraph::new(format!(
+                "Failed to read archive entry\n > {}\n\nError: {}",
+                path.display(),
+                e
+            ))
+            .block(block)
+            .render(area, buf),
+            None => Paragraph::new("Extracting...".dark_gray())
+                .block(block)
+                .render(area, buf),
+        }
+    }
+
+    // Lists what's in the hovered archive without extracting anything, huge
+    // archives only have their first entries read
+    pub fn render_archive_contents(
+        &mut self,
+        path: &Path,
+        kind: ArchiveKind,
+        block: Block,
+        area: Rect,
+        buf: &mut Buffer,
+    ) {
+        let modified = path
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok();
+
+        let Some(archive_loader) = &mut self.archive_loader else {
+            return;
+        };
+
+        let Some(contents) = archive_loader.listed(path, modified) else {
+            archive_loader.request(ArchiveRequest::List {
+                path: path.to_path_buf(),
+                modified,
+                kind,
+            });
+            Paragraph::new("Reading archive...".dark_gray())
+                .block(block)
+                .render(area, buf);
+            return;
+        };
+
+        let entries = match &contents.entries {
+            Ok(entries) => entries,
+            Err(e) => {
+                Paragraph::new(format!(
+                    "Failed to read archive\n > {}\n\nError: {}",
+                    path.display(),
+                    e
+                ))
+                .block(block)
+                .render(area, buf);
+                return;
+            }
+        };
+
+        let rows: Vec<Row> = entries
+            .iter()
+            .map(|entry| {
+                if entry.is_dir {
+                    return Row::new(vec![format!("{}/", entry.path.display())]).blue();
+                }
+
+                let ratio = match entry.compressed_size {
+                    Some(compressed_size) if entry.size > 0 => {
+                        format!("{}%", compressed_size * 100 / entry.size)
+                    }
+                    _ => String::from("-"),
+                };
+
+                Row::new(vec![
+                    entry.path.display().to_string(),
+                    format_size(entry.size),
+                    ratio,
+                ])
+            })
+            .collect();
+
+        let total_size: u64 = entries.iter().map(|entry| entry.size).sum();
+
+        // tar archives are compressed as a whole, so their ratio comes from the
+        // archive's own size, which is only meaningful once every entry is read
+        let compressed_size = if kind == ArchiveKind::Zip {
+            entries
+                .iter()
+                .filter_map(|entry| entry.compressed_size)
+                .sum::<u64>()
+        } else if !contents.truncated {
+            path.metadata().map(|metadata| metadata.len()).unwrap_or(0)
+        } else {
+            0
+        };
+
+        let mut summary = if contents.truncated {
+            format!(" First {} entries", entries.len())
+        } else {
+            let noun = if entries.len() == 1 {
+                "entry"
+            } else {
+                "entries"
+            };
+            format!(" {} {}, {}", entries.len(), noun, format_size(total_size))
+        };
+
+        if total_size > 0 && compressed_size > 0 {
+            summary.push_str(&format!(
+                ", compressed to {}%",
+                compressed_size * 100 / total_size
+            ));
+        }
+        summary.push(' ');
+
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Fill(1),
+                Constraint::Length(10),
+                Constraint::Length(5),
+            ],
+        )
+        .header(Row::new(vec!["Name", "Size", "Ratio"]).bold())
+        .block(block.title_bottom(Line::from(summary).right_aligned()));
+
+        Widget::render(table, area, buf);
+    }
+}
+
+impl ArchiveLoader {
+    pub fn new() -> Self {
+        let (request_sender, requests) = channel::<Vec<ArchiveRequest>>();
+        let (results, result_receiver) = channel();
+
+        let worker = thread::spawn(move || load_archives(requests, results));
+
+        Self {
+            requests: request_sender,
+            results: result_receiver,
+            worker,
+            pending: Vec::new(),
+            wanted: Vec::new(),
+            listed: VecDeque::new(),
+            extracted: Vec::new(),
+        }
+    }
+
+    pub fn listed(&self, path: &Path, modified: Option<SystemTime>) -> Option<&ArchiveContents> {
+        self.listed
+            .iter()
+            .find(|contents| contents.path == path && contents.modified == modified)
+    }
+
+    pub fn extracted(&self, request: &ExtractRequest) -> Option<&Result<PathBuf, String>> {
+        self.extracted
+            .iter()
+            .find(|(extracted, _)| extracted == request)
+            .map(|(_, result)| result)
+    }
+
+    pub fn request(&mut self, request: ArchiveRequest) {
+        if !self.wanted.contains(&request) {
+            self.wanted.push(request);
+        }
+    }
+
+    // Sends the archives and entries requested this frame to the worker
+    pub fn finish_frame(&mut self) {
+        let wanted = std::mem::take(&mut self.wanted);
+        if wanted != self.pending && self.requests.send(wanted.clone()).is_ok() {
+            self.pending = wanted;
+        }
+    }
+
+    pub fn is_loading(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    pub fn receive(&mut self) {
+        while let Ok(result) = self.results.try_recv() {
+            match result {
+                ArchiveResult::Listed(contents) => {
+                    self.pending.retain(|pending| {
+                        !matches!(pending, ArchiveRequest::List { path, modified, .. }
+                            if *path == contents.path && *modified == contents.modified)
+                    });
+
+                    self.listed.retain(|listed| listed.path != contents.path);
+                    self.listed.push_front(contents);
+                    self.listed.truncate(LISTING_CACHE_SIZE);
+                }
+                ArchiveResult::Extracted(request, result) => {
+                    self.pending
+                        .retain(|pending| *pending != ArchiveRequest::Extract(request.clone()));
+                    self.extracted.push((request, result));
+                }
+            }
+        }
+    }
+
+    // Waits for the worker to stop so the extracted files are gone before
+    // the process exits
+    pub fn shut_down(self) {
+        drop(self.requests);
+        drop(self.results);
+        let _ = self.worker.join();
+    }
+}
+
+// The preview directory is only readable by the current user and never one
+// left over from an earlier session, every entry gets a new directory in it
+// so nothing in there is ever overwritten or followed
+fn load_archives(requests: Receiver<Vec<ArchiveRequest>>, results: Sender<ArchiveResult>) {
+    let preview_dir = create_preview_dir();
+    let mut queue = VecDeque::new();
+    let mut extracted: usize = 0;
+
+    loop {
+        if queue.is_empty() {
+            match requests.recv() {
+                Ok(batch) => queue = VecDeque::from(batch),
+                Err(_) => return,
+            }
+        }
+
+        // a newer request replaces whatever is left of the old one, so
+        // archives scrolled past quickly are never read
+        loop {
+            match requests.try_recv() {
+                Ok(batch) => queue = VecDeque::from(batch),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return,
+            }
+        }
+
+        let Some(request) = queue.pop_front() else {
+            continue;
+        };
+
+        let result = match request {
+            ArchiveRequest::List {
+                path,
+                modified,
+                kind,
+            } => ArchiveResult::Listed(list_contents(path, modified, kind)),
+            ArchiveRequest::Extract(request) => {
+                let result = match &preview_dir {
+                    Ok(preview_dir) => {
+                        extracted += 1;
+                        extract_preview(&request, &preview_dir.path().join(extracted.to_string()))
+                            .map_err(|e| e.to_string())
+                    }
+                    Err(e) => Err(format!("Couldn't create the preview directory: {}", e)),
+                };
+                ArchiveResult::Extracted(request, result)
+            }
+        };
+
+        if results.send(result).is_err() {
+            return;
+        }
+    }
+}
+
+fn list_contents(
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    kind: ArchiveKind,
+) -> ArchiveContents {
+    let entries = read_entries(&path, kind, MAX_LISTED_ENTRIES + 1).map_err(|e| e.to_string());
+    let truncated = entries
+        .as_ref()
+        .is_ok_and(|entries| entries.len() > MAX_LISTED_ENTRIES);
+
+    ArchiveContents {
+        path,
+        modified,
+        entries: entries.map(|mut entries| {
+            entries.truncate(MAX_LISTED_ENTRIES);
+            entries
+        }),
+        truncated,
+    }
+}
+
+fn create_preview_dir() -> io::Result<TempDir> {
+    let mut builder = tempfile::Builder::new();
+    builder.prefix("gravily-");
+
+    #[cfg(unix)]
+    builder.permissions(Permissions::from_mode(0o700));
+
+    builder.tempdir()
+}
+
+fn extract_preview(request: &ExtractRequest, dir: &Path) -> io::Result<PathBuf> {
+    let file_name = request
+        .entry
+        .path
+        .file_name()
+        .ok_or_else(|| io::Error::other("Entry has no file name"))?;
+
+    create_dir(dir)?;
+    let preview_path = dir.join(file_name);
+
+    let mut file = File::options()
+        .write(true)
+        .create_new(true)
+        .open(&preview_path)?;
+    if let Err(e) = copy_entry(
+        &request.archive_path,
+        request.kind,
+        &request.entry,
+        &mut file,
+    ) {
+        let _ = remove_file(&preview_path);
+        return Err(e);
+    }
+
+    Ok(preview_path)
+}
+
+// Keeps only the normal components of an entry's path, so absolute paths and
+// ".." can't point outside the archive
+fn sanitize_entry_path(path: &Path) -> Option<PathBuf> {
+    let mut sanitized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => sanitized.push(part),
+            Component::CurDir | Component::RootDir | Component::Prefix(_) => {}
+            Component::ParentDir => return None,
+        }
+    }
+
+    if sanitized.as_os_str().is_empty() {
+        return None;
+    }
+
+    Some(sanitized)
+}
+
+pub fn open_tar(path: &Path, kind: ArchiveKind) -> io::Result<tar::Archive<Box<dyn Read>>> {
+    let file = BufReader::new(File::open(path)?);
+
+    let reader: Box<dyn Read> = match kind {
+        ArchiveKind::TarGz => Box::new(GzDecoder::new(file)),
+        ArchiveKind::TarXz => Box::new(XzDecoder::new(file)),
+        ArchiveKind::TarZst => Box::new(zstd::Decoder::with_buffer(file)?),
+        _ => Box::new(file),
+    };
+
+    Ok(tar::Archive::new(reader))
+}
+
+// Reads at most `limit` entries, without extracting any of them
+pub fn read_entries(path: &Path, kind: ArchiveKind, limit: usize) -> io::Result<Vec<ArchiveEntry>> {
+    let mut entries = Vec::new();
+
+    if kind == ArchiveKind::Zip {
+        let mut archive =
+            ZipArchive::new(BufReader::new(File::open(path)?)).map_err(io::Error::other)?;
+
+        for index in 0..archive.len().min(limit) {
+            let file = archive.by_index_raw(index).map_err(io::Error::other)?;
+
+            let Some(entry_path) = file
+                .name()
+                .ok()
+                .and_then(|name| sanitize_entry_path(Path::new(name.as_ref())))
+            else {
+                continue;
+            };
+
+            entries.push(ArchiveEntry {
+                path: entry_path,
+                index,
+                size: file.size(),
+                compressed_size: Some(file.compressed_size()),
+                is_dir: file.is_dir(),
+            });
+        }
+
+        return Ok(entries);
+    }
+
+    let mut archive = open_tar(path, kind)?;
+
+    for (index, entry) in archive.entries()?.enumerate().take(limit) {
+        let entry = entry?;
+
+        let Some(entry_path) = entry
+            .path()
+            .ok()
+            .and_then(|entry_path| sanitize_entry_path(&entry_path))
+        else {
+            continue;
+        };
+
+        entries.push(ArchiveEntry {
+            path: entry_path,
+            index,
+            size: entry.header().size().unwrap_or(0),
+            compressed_size: None,
+            is_dir: entry.header().entry_type().is_dir(),
+        });
+    }
+
+    Ok(entries)
+}
+
+pub fn copy_entry(
+    path: &Path,
+    kind: ArchiveKind,
+    entry: &ArchiveEntry,
+    writer: &mut impl Write,
+) -> io::Result<u64> {
+    if kind == ArchiveKind::Zip {
+        let mut archive =
+            ZipArchive::new(BufReader::new(File::open(path)?)).map_err(io::Error::other)?;
+        let mut file = archive.by_index(entry.index).map_err(io::Error::other)?;
+
+        return io::copy(&mut file, writer);
+    }
+
+    let mut archive = open_tar(path, kind)?;
+
+    match archive.entries()?.nth(entry.index) {
+        Some(tar_entry) => io::copy(&mut tar_entry?, writer),
+        None => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "Entry not found in archive",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sanitize_entry_path;
+
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn keeps_relative_paths() {
+        assert_eq!(
+            sanitize_entry_path(Path::new("docs/readme.txt")),
+            Some(PathBuf::from("docs/readme.txt"))
+        );
+    }
+
+    #[test]
+    fn drops_root_and_current_dir() {
+        assert_eq!(
+            sanitize_entry_path(Path::new("/etc/passwd")),
+            Some(PathBuf::from("etc/passwd"))
+        );
+        assert_eq!(
+            sanitize_entry_path(Path::new("./a/./b")),
+            Some(PathBuf::from("a/b"))
+        );
+    }
+
+    #[test]
+    fn rejects_parent_dirs() {
+        assert_eq!(sanitize_entry_path(Path::new("../outside")), None);
+        assert_eq!(sanitize_entry_path(Path::new("a/../../outside")), None);
+    }
+
+    #[test]
+    fn rejects_empty_paths() {
+        assert_eq!(sanitize_entry_path(Path::new("")), None);
+        assert_eq!(sanitize_entry_path(Path::new("/")), None);
+        assert_eq!(sanitize_entry_path(Path::new("./")), None);
+    }
+}
@@ -0,0 +1,322 @@
+use super::FileManager;
+use crate::gravily::config::data_dir;
+use crate::gravily::finder_handling::{fuzzy_match, highlight_positions};
+use crate::gravily::helper_functions::{escape_field, path_bytes, path_from_bytes, unescape_field};
+use crate::gravily::{Bookmark, InputMode};
+
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Flex, Layout, Rect},
+    style::Stylize,
+    symbols::border,
+    text::{Line, Span},
+    widgets::{Block, List, ListState, StatefulWidget},
+};
+use tui_input::Input;
+
+use std::fs::{create_dir_all, read_to_string, write};
+use std::io::ErrorKind;
+
+impl Bookmark {
+    // name<TAB>path<TAB>key lines, the key can be left out. The name and path
+    // are escaped, see escape_field
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.split('\t');
+        let name = String::from_utf8_lossy(&unescape_field(fields.next()?)).into_owned();
+        let path = path_from_bytes(unescape_field(fields.next()?));
+        let key = fields.next().and_then(|key| key.chars().next());
+
+        Some(Self { name, path, key })
+    }
+
+    fn to_line(&self) -> String {
+        let mut line = format!(
+            "{}\t{}",
+            escape_field(self.name.as_bytes()),
+            escape_field(&path_bytes(&self.path))
+        );
+        if let Some(key) = self.key {
+            line.push('\t');
+            line.push(key);
+        }
+
+        line
+    }
+}
+
+impl FileManager {
+    // A missing bookmarks file just means there are none yet
+    pub fn load_bookmarks(&mut self) {
+        let Some(path) = data_dir().map(|dir| dir.join("bookmarks")) else {
+            return;
+        };
+
+        match read_to_string(&path) {
+            Ok(contents) => {
+                self.bookmarks = contents
+                    .lines()
+                    .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+                    .filter_map(Bookmark::parse)
+                    .collect();
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => self.error = format!("Error reading bookmarks {}: {}", path.display(), e),
+        }
+    }
+
+    fn save_bookmarks(&mut self) {
+        let Some(dir) = data_dir() else {
+            self.error = String::from("Error saving bookmarks: No HOME or XDG_DATA_HOME");
+            return;
+        };
+
+        let contents: String = self
+            .bookmarks
+            .iter()
+            .map(|bookmark| bookmark.to_line() + "\n")
+            .collect();
+
+        if let Err(e) = create_dir_all(&dir).and_then(|_| write(dir.join("bookmarks"), contents)) {
+            self.error = format!("Error saving bookmarks {}: {}", dir.display(), e);
+        }
+    }
+
+    // Bookmarks the current directory under `key`, taking the key away from
+    // whatever had it before
+    pub fn set_bookmark(&mut self, key: char) {
+        self.input_mode = InputMode::Navigation;
+
+        if self.is_in_archive() {
+            self.error = String::from("Can't bookmark a place inside an archive");
+            return;
+        }
+
+        for bookmark in &mut self.bookmarks {
+            if bookmark.key == Some(key) {
+                bookmark.key = None;
+            }
+        }
+
+        match self
+            .bookmarks
+            .iter_mut()
+            .find(|bookmark| bookmark.path == self.path)
+        {
+            Some(bookmark) => bookmark.key = Some(key),
+            None => self.bookmarks.push(Bookmark {
+                name: self.path.file_name().map_or_else(
+                    || self.path.display().to_string(),
+                    |name| name.to_string_lossy().to_string(),
+                ),
+                path: self.path.clone(),
+                key: Some(key),
+            }),
+        }
+
+        self.save_bookmarks();
+    }
+
+    pub fn jump_to_bookmark_key(&mut self, key: char) {
+        self.input_mode = InputMode::Navigation;
+
+        match self
+            .bookmarks
+            .iter()
+            .position(|bookmark| bookmark.key == Some(key))
+        {
+            Some(index) => self.jump_to_bookmark(index),
+            None => self.error = format!("No bookmark on '{}'", key),
+        }
+    }
+
+    fn jump_to_bookmark(&mut self, index: usize) {
+        let path = self.bookmarks[index].path.clone();
+
+        if path.is_dir() {
+            self.open_dir(&path);
+        } else if path.exists() {
+            self.reveal_path(&path);
+        } else {
+            self.error = format!(
+                "Bookmark {:?} points to {}, which doesn't exist anymore",
+                self.bookmarks[index].name,
+                path.display()
+            );
+        }
+    }
+
+    pub fn is_bookmark_picker_open(&self) -> bool {
+        self.bookmark_picker.is_some()
+    }
+
+    pub fn open_bookmark_picker(&mut self) {
+        self.bookmark_picker = Some(ListState::default().with_selected(Some(0)));
+        self.input.reset();
+        self.input_mode = InputMode::Bookmarks;
+    }
+
+    pub fn close_bookmark_picker(&mut self) {
+        self.bookmark_picker = None;
+        self.input.reset();
+        self.input_mode = InputMode::Navigation;
+    }
+
+    pub fn update_bookmark_picker(&mut self) {
+        if let Some(state) = &mut self.bookmark_picker {
+            state.select(Some(0));
+        }
+    }
+
+    pub fn move_bookmark_selection(&mut self, down: bool) {
+        let count = self.picked_bookmarks().len();
+
+        if let Some(state) = &mut self.bookmark_picker {
+            let selected = state.selected().unwrap_or(0);
+            let selected = if down {
+                (selected + 1).min(count.saturating_sub(1))
+            } else {
+                selected.saturating_sub(1)
+            };
+            state.select(Some(selected));
+        }
+    }
+
+    pub fn open_picked_bookmark(&mut self) {
+        let picked = self.selected_picked_bookmark();
+        self.close_bookmark_picker();
+
+        if let Some(index) = picked {
+            self.jump_to_bookmark(index);
+        }
+    }
+
+    pub fn remove_picked_bookmark(&mut self) {
+        if let Some(index) = self.selected_picked_bookmark() {
+            self.bookmarks.remove(index);
+            self.save_bookmarks();
+            self.move_bookmark_selection(false);
+        }
+    }
+
+    pub fn start_naming_picked_bookmark(&mut self) {
+        if let Some(index) = self.selected_picked_bookmark() {
+            self.bookmark_picker = None;
+            self.input = Input::new(self.bookmarks[index].name.clone());
+            self.input_mode = InputMode::NameBookmark(index);
+        }
+    }
+
+    pub fn name_bookmark(&mut self, name: &str) {
+        let InputMode::NameBookmark(index) = self.input_mode else {
+            return;
+        };
+
+        if name.trim().is_empty() {
+            self.error = String::from("A bookmark needs a name");
+        } else {
+            self.bookmarks[index].name = name.to_string();
+            self.save_bookmarks();
+        }
+
+        self.finish_naming_bookmark();
+    }
+
+    // Back to the picker, on the bookmark that was being named
+    pub fn finish_naming_bookmark(&mut self) {
+        let InputMode::NameBookmark(index) = self.input_mode else {
+            return;
+        };
+
+        self.open_bookmark_picker();
+        if let Some(state) = &mut self.bookmark_picker {
+            state.select(Some(index));
+        }
+    }
+
+    fn selected_picked_bookmark(&self) -> Option<usize> {
+        let selected = self.bookmark_picker.as_ref()?.selected()?;
+        self.picked_bookmarks()
+            .get(selected)
+            .map(|(index, _)| *index)
+    }
+
+    // Indices of the bookmarks whose name or path fit the query, best first,
+    // with the matched characters of "name path"
+    fn picked_bookmarks(&self) -> Vec<(usize, Vec<usize>)> {
+        let query = self.input.value();
+        let case_sensitive = query.chars().any(char::is_uppercase);
+
+        let mut picked: Vec<(usize, i64, Vec<usize>)> = self
+            .bookmarks
+            .iter()
+            .enumerate()
+            .filter_map(|(index, bookmark)| {
+                let text = format!("{} {}", bookmark.name, bookmark.path.display());
+                let (score, positions) = fuzzy_match(&text, query, case_sensitive)?;
+                Some((index, score, positions))
+            })
+            .collect();
+
+        picked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        picked
+            .into_iter()
+            .map(|(index, _, positions)| (index, positions))
+            .collect()
+    }
+
+    pub fn render_bookmark_picker(&mut self, area: Rect, buf: &mut Buffer) {
+        let picked = self.picked_bookmarks();
+
+        let [area] = Layout::horizontal([Constraint::Percentage(70)])
+            .flex(Flex::Center)
+            .areas(area);
+        let [area] = Layout::vertical([Constraint::Percentage(70)])
+            .flex(Flex::Center)
+            .areas(area);
+
+        let block = Block::bordered()
+            .title(" Bookmarks ")
+            .title_bottom(
+                Line::from(
+                    " Enter to go there, Ctrl-r to rename, Ctrl-d to remove, m<key> to add ",
+                )
+                .right_aligned(),
+            )
+            .border_set(border::ROUNDED);
+
+        let items: Vec<Line> = picked
+            .iter()
+            .map(|(index, positions)| {
+                let bookmark = &self.bookmarks[*index];
+                let key = bookmark
+                    .key
+                    .map_or(String::from("   "), |key| format!("'{} ", key));
+
+                let mut spans: Vec<Span> = vec![key.blue().bold()];
+                spans.extend(
+                    highlight_positions(
+                        &format!("{} {}", bookmark.name, bookmark.path.display()),
+                        positions,
+                    )
+                    .spans,
+                );
+
+                if !bookmark.path.exists() {
+                    spans.push(" (missing)".red().bold());
+                    return Line::from(spans).dark_gray();
+                }
+
+                Line::from(spans)
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_symbol("> ")
+            .highlight_spacing(ratatui::widgets::HighlightSpacing::Always);
+
+        if let Some(state) = &mut self.bookmark_picker {
+            StatefulWidget::render(list, area, buf, state);
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use super::FileManager;
+
+use ratatui::{style::Stylize, text::Line};
+
+use crate::gravily::helper_functions::path_bytes;
+
+use std::fs::{File, write};
+use std::io::{self, IsTerminal, Write};
+
+impl FileManager {
+    pub fn is_chooser(&self) -> bool {
+        self.options.chooser_file.is_some()
+            || self.options.chooser_multiple
+            || self.options.chooser_dir
+    }
+
+    // Enter picks hovered files, directories are still entered
+    pub fn is_hovered_choosable(&self) -> bool {
+        self.is_chooser()
+            && !self.options.chooser_dir
+            && !self.is_in_archive()
+            && self.is_hovering()
+            && !self.get_hovered_dir().is_dir()
+    }
+
+    // Chooses the marked items, or else the hovered file or the current
+    // directory, and quits
+    pub fn confirm_choice(&mut self) {
+        if self.is_in_archive() {
+            self.error = String::from("Can't choose from inside an archive");
+            return;
+        }
+
+        let choose_dirs = self.options.chooser_dir;
+        let mut chosen = if choose_dirs && self.marked.is_empty() {
+            vec![self.path.clone()]
+        } else {
+            self.selected_paths()
+        };
+        chosen.retain(|path| path.is_dir() == choose_dirs);
+
+        let kind = if choose_dirs { "directories" } else { "files" };
+        if chosen.is_empty() {
+            self.error = format!("Nothing to choose, only {} can be chosen", kind);
+            return;
+        }
+        if chosen.len() > 1 && !self.options.chooser_multiple {
+            self.error = format!("Only one of the marked {} can be chosen", kind);
+            return;
+        }
+
+        self.chosen = chosen;
+        self.exit();
+    }
+
+    pub fn chooser_banner(&self) -> Option<Line<'static>> {
+        if !self.is_chooser() {
+            return None;
+        }
+
+        let what = match (self.options.chooser_dir, self.options.chooser_multiple) {
+            (false, false) => "a file",
+            (false, true) => "files",
+            (true, false) => "a directory",
+            (true, true) => "directories",
+        };
+        let how = if self.options.chooser_dir {
+            "Ctrl-s picks the current or marked ones"
+        } else {
+            "Enter or Ctrl-s picks the hovered or marked ones"
+        };
+
+        Some(Line::from(
+            format!(" Choose {}: {}, q cancels ", what, how)
+                .black()
+                .on_yellow()
+                .bold(),
+        ))
+    }
+
+    fn chooses_to_stdout(&self) -> bool {
+        self.is_chooser()
+            && self
+                .options
+                .chooser_file
+                .as_ref()
+                .is_none_or(|file| file.as_os_str() == "-")
+    }
+
+    // Called before the terminal is set up. When the paths go to a stdout that
+    // isn't the terminal, like in vim "$(gravily --chooser-file -)", the screen
+    // is drawn on /dev/tty and stdout is kept aside for the paths
+    #[cfg(unix)]
+    pub fn set_up_chooser_output(&mut self) -> io::Result<()> {
+        use std::fs::OpenOptions;
+        use std::os::fd::{AsFd, AsRawFd};
+
+        if !self.chooses_to_stdout() || io::stdout().is_terminal() {
+            return Ok(());
+        }
+
+        let tty = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open("/dev/tty")
+            .map_err(|e| io::Error::new(e.kind(), format!("/dev/tty: {}", e)))?;
+        let stdout = io::stdout().as_fd().try_clone_to_owned()?;
+
+        // SAFETY: both descriptors are open, fd 1 just becomes another copy of
+        // the terminal's
+        if unsafe { libc::dup2(tty.as_raw_fd(), libc::STDOUT_FILENO) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+
+        self.chooser_output = Some(File::from(stdout));
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    pub fn set_up_chooser_output(&mut self) -> io::Result<()> {
+        if self.chooses_to_stdout() && !io::stdout().is_terminal() {
+            return Err(io::Error::other(
+                "Choosing to stdout needs it to be the terminal here, use --chooser-file",
+            ));
+        }
+
+        Ok(())
+    }
+
+    // Called once the terminal is restored, so stdout is free again
+    pub fn write_results(&self) -> io::Result<()> {
+        if self.cd_on_exit {
+            self.write_choosedir()?;
+        }
+
+        if !self.is_chooser() || self.chosen.is_empty() {
+            return Ok(());
+        }
+
+        let mut contents = Vec::new();
+        for path in &self.chosen {
+            contents.extend(path_bytes(path));
+            contents.push(b'\n');
+        }
+
+        match (&self.options.chooser_file, &self.chooser_output) {
+            (Some(file), _) if file.as_os_str() != "-" => write(file, contents)
+                .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", file.display(), e))),
+            (_, Some(output)) => (&*output).write_all(&contents),
+            _ => io::stdout().write_all(&contents),
+        }
+    }
+}
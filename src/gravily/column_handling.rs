@@ -0,0 +1,344 @@
+use super::FileManager;
+use crate::gravily::Column;
+use crate::gravily::helper_functions::format_size;
+
+use chrono::{DateTime, Local};
+use ratatui::{
+    layout::Constraint,
+    text::Line,
+    widgets::{Cell, Row},
+};
+
+#[cfg(unix)]
+use std::collections::HashMap;
+use std::fs::{Metadata, read_dir, read_link};
+use std::io;
+use std::iter;
+use std::ops::Range;
+use std::path::Path;
+#[cfg(unix)]
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+
+// Names shorter than this still get all of their width before any column
+const MIN_NAME_WIDTH: usize = 20;
+
+const COLUMNS: [(&str, Column); 8] = [
+    ("size", Column::Size),
+    ("permissions", Column::Permissions),
+    ("owner", Column::Owner),
+    ("modified", Column::Modified),
+    ("created", Column::Created),
+    ("accessed", Column::Accessed),
+    ("link_target", Column::LinkTarget),
+    ("item_count", Column::ItemCount),
+];
+
+impl Column {
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = match name {
+            "perms" => "permissions",
+            "mtime" => "modified",
+            "atime" => "accessed",
+            "link" => "link_target",
+            "items" => "item_count",
+            _ => name,
+        };
+
+        COLUMNS
+            .into_iter()
+            .find(|(column_name, _)| *column_name == name)
+            .map(|(_, column)| column)
+    }
+
+    pub fn name(self) -> &'static str {
+        COLUMNS
+            .into_iter()
+            .find(|(_, column)| *column == self)
+            .map_or("", |(name, _)| name)
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            Column::Size => "Size",
+            Column::Permissions => "Permissions",
+            Column::Owner => "Owner",
+            Column::Modified => "Modified",
+            Column::Created => "Created",
+            Column::Accessed => "Accessed",
+            Column::LinkTarget => "Link",
+            Column::ItemCount => "Items",
+        }
+    }
+
+    fn is_numeric(self) -> bool {
+        matches!(self, Column::Size | Column::ItemCount)
+    }
+
+    fn value(self, path: &Path, metadata: &Metadata) -> String {
+        match self {
+            Column::Size if metadata.is_dir() => String::new(),
+            Column::Size => format_size(metadata.len()),
+            Column::Permissions => permissions_string(metadata),
+            Column::Owner => owner_string(metadata),
+            Column::Modified => format_time(metadata.modified()),
+            Column::Created => format_time(metadata.created()),
+            Column::Accessed => format_time(metadata.accessed()),
+            Column::LinkTarget if metadata.is_symlink() => read_link(path)
+                .map(|target| format!("-> {}", target.display()))
+                .unwrap_or_default(),
+            Column::LinkTarget => String::new(),
+            // follows symlinks, so links to directories get a count too
+            Column::ItemCount if path.is_dir() => read_dir(path)
+                .map(|items| items.count().to_string())
+                .unwrap_or_else(|_| String::from("-")),
+            Column::ItemCount => String::new(),
+        }
+    }
+}
+
+impl TryFrom<String> for Column {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        Column::from_name(&name).ok_or_else(|| format!("Unknown column {:?}", name))
+    }
+}
+
+impl FileManager {
+    pub fn columns_description(&self) -> String {
+        self.columns
+            .iter()
+            .map(|column| column.name())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    // Columns are typed as space or comma separated names, nothing at all
+    // leaves only the names
+    pub fn set_columns(&mut self) {
+        let value = self.input.value_and_reset();
+
+        match parse_columns(&value) {
+            Ok(columns) => self.columns = columns,
+            Err(e) => self.error = e,
+        }
+    }
+
+    // Rows of the main listing for the items in `visible`, the only ones that
+    // are drawn. Columns that don't fit in `width` next to the names are left
+    // out, starting from the last one
+    pub fn column_rows(
+        &self,
+        visible: Range<usize>,
+        width: u16,
+    ) -> (Vec<Row<'static>>, Vec<Constraint>, usize) {
+        let items = &self.path_items[visible];
+
+        let values: Vec<Vec<String>> = items
+            .iter()
+            .map(|file_name| {
+                let path = self.path.join(file_name);
+
+                let metadata = if self.is_in_archive() {
+                    None
+                } else {
+                    path.symlink_metadata().ok()
+                };
+
+                self.columns
+                    .iter()
+                    .map(|column| {
+                        metadata
+                            .as_ref()
+                            .map(|metadata| column.value(&path, metadata))
+                            .unwrap_or_default()
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let name_width = items
+            .iter()
+            .map(|file_name| file_name.to_string_lossy().chars().count())
+            .max()
+            .unwrap_or(0)
+            .min(MIN_NAME_WIDTH);
+
+        let mut widths = Vec::new();
+        let mut used_width = name_width;
+
+        for (column_index, column) in self.columns.iter().enumerate() {
+            let column_width = values
+                .iter()
+                .map(|row| row[column_index].chars().count())
+                .chain(iter::once(column.title().len()))
+                .max()
+                .unwrap_or(0);
+
+            // every column is separated by a space
+            if used_width + column_width + 1 > width as usize {
+                break;
+            }
+
+            used_width += column_width + 1;
+            widths.push(column_width);
+        }
+
+        let rows = items
+            .iter()
+            .zip(values)
+            .map(|(file_name, row)| {
+                let name =
+                    Cell::from(self.highlight_matches(file_name.to_string_lossy().to_string()));
+
+                let cells =
+                    self.columns
+                        .iter()
+                        .zip(row)
+                        .take(widths.len())
+                        .map(|(column, value)| {
+                            if column.is_numeric() {
+                                Cell::from(Line::from(value).right_aligned())
+                            } else {
+                                Cell::from(value)
+                            }
+                        });
+
+                Row::new(iter::once(name).chain(cells))
+            })
+            .collect();
+
+        let shown_columns = widths.len();
+        let constraints = iter::once(Constraint::Fill(1))
+            .chain(
+                widths
+                    .into_iter()
+                    .map(|width| Constraint::Length(width as u16)),
+            )
+            .collect();
+
+        (rows, constraints, shown_columns)
+    }
+
+    pub fn column_header(&self, shown_columns: usize) -> Option<Row<'static>> {
+        if self.columns.is_empty() {
+            return None;
+        }
+
+        let titles = iter::once("Name").chain(
+            self.columns
+                .iter()
+                .take(shown_columns)
+                .map(|column| column.title()),
+        );
+
+        Some(Row::new(titles.map(|title| title.to_string())))
+    }
+}
+
+pub fn parse_columns(value: &str) -> Result<Vec<Column>, String> {
+    let mut columns = Vec::new();
+
+    for name in value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|name| !name.is_empty())
+    {
+        let column = Column::try_from(name.to_lowercase())?;
+
+        if !columns.contains(&column) {
+            columns.push(column);
+        }
+    }
+
+    Ok(columns)
+}
+
+fn format_time(time: io::Result<SystemTime>) -> String {
+    match time {
+        Ok(time) => DateTime::<Local>::from(time)
+            .format("%Y-%m-%d %H:%M")
+            .to_string(),
+        Err(_) => String::from("-"),
+    }
+}
+
+#[cfg(unix)]
+fn permissions_string(metadata: &Metadata) -> String {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = metadata.permissions().mode();
+
+    let kind = if metadata.is_symlink() {
+        'l'
+    } else if metadata.is_dir() {
+        'd'
+    } else {
+        '-'
+    };
+
+    let mut permissions = String::from(kind);
+    for shift in [6, 3, 0] {
+        let bits = mode >> shift;
+        permissions.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        permissions.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        permissions.push(if bits & 0o1 != 0 { 'x' } else { '-' });
+    }
+
+    permissions
+}
+
+#[cfg(not(unix))]
+fn permissions_string(metadata: &Metadata) -> String {
+    if metadata.permissions().readonly() {
+        String::from("r-")
+    } else {
+        String::from("rw")
+    }
+}
+
+#[cfg(unix)]
+fn owner_string(metadata: &Metadata) -> String {
+    use std::os::unix::fs::MetadataExt;
+
+    let user = user_name(metadata.uid()).unwrap_or_else(|| metadata.uid().to_string());
+    let group = group_name(metadata.gid()).unwrap_or_else(|| metadata.gid().to_string());
+
+    format!("{}:{}", user, group)
+}
+
+#[cfg(unix)]
+pub fn user_name(uid: u32) -> Option<String> {
+    static USERS: OnceLock<Mutex<HashMap<u32, Option<String>>>> = OnceLock::new();
+
+    cached_name(&USERS, uid, |uid| {
+        uzers::get_user_by_uid(uid).map(|user| user.name().to_string_lossy().into_owned())
+    })
+}
+
+#[cfg(unix)]
+fn group_name(gid: u32) -> Option<String> {
+    static GROUPS: OnceLock<Mutex<HashMap<u32, Option<String>>>> = OnceLock::new();
+
+    cached_name(&GROUPS, gid, |gid| {
+        uzers::get_group_by_gid(gid).map(|group| group.name().to_string_lossy().into_owned())
+    })
+}
+
+// Looked up through the system's user database (getpwuid_r and getgrgid_r, so
+// LDAP and the like work too) once per id and kept for the whole session
+#[cfg(unix)]
+fn cached_name(
+    cache: &OnceLock<Mutex<HashMap<u32, Option<String>>>>,
+    id: u32,
+    lookup: impl FnOnce(u32) -> Option<String>,
+) -> Option<String> {
+    let mut names = cache.get_or_init(Mutex::default).lock().ok()?;
+
+    names.entry(id).or_insert_with(|| lookup(id)).clone()
+}
+
+#[cfg(not(unix))]
+fn owner_string(_metadata: &Metadata) -> String {
+    String::from("-")
+}
@@ -0,0 +1,39 @@
+use super::FileManager;
+
+impl FileManager {
+    // Runs what was typed after ':'
+    pub fn run_command(&mut self) {
+        self.error = String::new();
+
+        let command = self.input.value_and_reset();
+        let words: Vec<&str> = command.split_whitespace().collect();
+
+        let result = match words.as_slice() {
+            [] => Ok(()),
+            ["sort", arguments @ ..] => self.apply_sort_command(arguments),
+            ["find", query @ ..] => {
+                self.start_find(&query.join(" "));
+                Ok(())
+            }
+            ["z", query @ ..] => {
+                self.jump(&query.join(" "));
+                Ok(())
+            }
+            ["cd"] => {
+                self.goto_path("~");
+                Ok(())
+            }
+            ["cd", ..] => {
+                // keeps the spaces of paths that have them
+                self.goto_path(command.trim_start()[2..].trim());
+                Ok(())
+            }
+            ["import", "zoxide"] => self.import_zoxide(),
+            [name, ..] => Err(format!("Unknown command {:?}", name)),
+        };
+
+        if let Err(e) = result {
+            self.error = e;
+        }
+    }
+}
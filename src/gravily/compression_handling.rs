@@ -0,0 +1,343 @@
+use super::FileManager;
+use crate::gravily::archive_handling::{archive_stem, open_tar};
+use crate::gravily::{ArchiveKind, JobUpdate};
+
+use flate2::{Compression, write::GzEncoder};
+use xz2::write::XzEncoder;
+use zip::{ZipArchive, ZipWriter, write::SimpleFileOptions};
+
+use std::fs::{File, create_dir, create_dir_all, read_dir, read_link, remove_file};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+
+impl FileManager {
+    // Packs the marked (or hovered) items into the archive named by the input,
+    // the extension decides the format
+    pub fn compress_selection(&mut self) {
+        self.error = String::new();
+
+        let archive_name = self.input.value_and_reset();
+        let archive_path = self.path.join(archive_name.trim());
+
+        let Some(kind) = ArchiveKind::from_path(&archive_path) else {
+            self.error = format!(
+                "Error compressing into {:?}: Use a .zip, .tar, .tar.gz, .tar.xz or .tar.zst name",
+                archive_name
+            );
+            return;
+        };
+
+        let paths = self.selected_paths();
+        if paths.is_empty() {
+            self.error = String::from("Error compressing: Nothing selected");
+            return;
+        }
+
+        let root = self.path.clone();
+
+        self.spawn_job(
+            format!("Compressing into {}", archive_name.trim()),
+            0,
+            move |updates| {
+                let mut entries = Vec::new();
+                let mut errors = Vec::new();
+
+                for path in paths {
+                    let name = path.strip_prefix(&root).unwrap_or(&path).to_path_buf();
+                    collect_entries(path, name, &mut entries, &mut errors);
+                }
+
+                let _ = updates.send(JobUpdate::Total(entries.len() + errors.len()));
+                for error in errors {
+                    let _ = updates.send(JobUpdate::Done(Err(error)));
+                }
+
+                if let Err(e) = write_archive(&archive_path, kind, &entries, &updates) {
+                    let _ = remove_file(&archive_path);
+                    let _ = updates.send(JobUpdate::Done(Err(format!(
+                        "{}: {}",
+                        archive_path.display(),
+                        e
+                    ))));
+                }
+            },
+        );
+    }
+
+    // Unpacks the hovered archive next to it, or into a new folder named
+    // after it
+    pub fn extract_archive(&mut self, into_folder: bool) {
+        self.error = String::new();
+
+        let archive_path = self.get_hovered_dir();
+
+        let (Some(kind), Some(stem)) = (
+            ArchiveKind::from_path(&archive_path),
+            archive_stem(&archive_path),
+        ) else {
+            self.error = format!(
+                "Error extracting {}: Not a zip or tar archive",
+                archive_path.display()
+            );
+            return;
+        };
+
+        let destination = if into_folder {
+            let destination = self.path.join(stem);
+
+            if let Err(e) = create_dir(&destination) {
+                self.error = format!("Error extracting into {}: {}", destination.display(), e);
+                return;
+            }
+
+            destination
+        } else {
+            self.path.clone()
+        };
+
+        let file_name = archive_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+
+        self.spawn_job(format!("Extracting {}", file_name), 0, move |updates| {
+            let result = match kind {
+                ArchiveKind::Zip => extract_zip(&archive_path, &destination, &updates),
+                _ => extract_tar(&archive_path, kind, &destination, &updates),
+            };
+
+            if let Err(e) = result {
+                let _ = updates.send(JobUpdate::Done(Err(format!(
+                    "{}: {}",
+                    archive_path.display(),
+                    e
+                ))));
+            }
+        });
+    }
+}
+
+// Adds `path` and, for directories, everything below it. Symlinks are stored
+// as links rather than followed
+fn collect_entries(
+    path: PathBuf,
+    name: PathBuf,
+    entries: &mut Vec<(PathBuf, PathBuf)>,
+    errors: &mut Vec<String>,
+) {
+    let is_dir = path
+        .symlink_metadata()
+        .map(|metadata| metadata.is_dir())
+        .unwrap_or(false);
+
+    entries.push((path.clone(), name.clone()));
+
+    if !is_dir {
+        return;
+    }
+
+    match read_dir(&path) {
+        Ok(children) => {
+            for child in children.filter_map(|child| child.ok()) {
+                collect_entries(child.path(), name.join(child.file_name()), entries, errors);
+            }
+        }
+        Err(e) => errors.push(format!("{}: {}", path.display(), e)),
+    }
+}
+
+fn write_archive(
+    archive_path: &Path,
+    kind: ArchiveKind,
+    entries: &[(PathBuf, PathBuf)],
+    updates: &Sender<JobUpdate>,
+) -> io::Result<()> {
+    let file = BufWriter::new(File::create_new(archive_path)?);
+
+    match kind {
+        ArchiveKind::Zip => write_zip(file, entries, updates),
+        ArchiveKind::Tar => write_tar(file, entries, updates)?.flush(),
+        ArchiveKind::TarGz => write_tar(
+            GzEncoder::new(file, Compression::default()),
+            entries,
+            updates,
+        )?
+        .finish()?
+        .flush(),
+        ArchiveKind::TarXz => write_tar(XzEncoder::new(file, 6), entries, updates)?
+            .finish()?
+            .flush(),
+        ArchiveKind::TarZst => write_tar(zstd::Encoder::new(file, 0)?, entries, updates)?
+            .finish()?
+            .flush(),
+    }
+}
+
+fn write_tar<W: Write>(
+    writer: W,
+    entries: &[(PathBuf, PathBuf)],
+    updates: &Sender<JobUpdate>,
+) -> io::Result<W> {
+    let mut builder = tar::Builder::new(writer);
+    builder.follow_symlinks(false);
+
+    for (path, name) in entries {
+        let result = builder
+            .append_path_with_name(path, name)
+            .map_err(|e| format!("{}: {}", path.display(), e));
+
+        if updates.send(JobUpdate::Done(result)).is_err() {
+            return Err(io::Error::other("Cancelled"));
+        }
+    }
+
+    builder.into_inner()
+}
+
+fn write_zip<W: Write + io::Seek>(
+    writer: W,
+    entries: &[(PathBuf, PathBuf)],
+    updates: &Sender<JobUpdate>,
+) -> io::Result<()> {
+    let mut zip = ZipWriter::new(writer);
+
+    for (path, name) in entries {
+        let result = append_zip_entry(&mut zip, path, name)
+            .map_err(|e| format!("{}: {}", path.display(), e));
+
+        if updates.send(JobUpdate::Done(result)).is_err() {
+            return Err(io::Error::other("Cancelled"));
+        }
+    }
+
+    zip.finish().map_err(io::Error::other)?.flush()
+}
+
+fn append_zip_entry<W: Write + io::Seek>(
+    zip: &mut ZipWriter<W>,
+    path: &Path,
+    name: &Path,
+) -> io::Result<()> {
+    // zip names always use forward slashes
+    let name = name
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+
+    let metadata = path.symlink_metadata()?;
+    let options = SimpleFileOptions::default();
+
+    #[cfg(unix)]
+    let options = {
+        use std::os::unix::fs::PermissionsExt;
+        options.unix_permissions(metadata.permissions().mode())
+    };
+
+    if metadata.is_symlink() {
+        let target = read_link(path)?;
+        zip.add_symlink(name, target.to_string_lossy(), options)
+            .map_err(io::Error::other)
+    } else if metadata.is_dir() {
+        zip.add_directory(name, options).map_err(io::Error::other)
+    } else {
+        zip.start_file(name, options).map_err(io::Error::other)?;
+        io::copy(&mut BufReader::new(File::open(path)?), zip).map(|_| ())
+    }
+}
+
+fn extract_zip(
+    archive_path: &Path,
+    destination: &Path,
+    updates: &Sender<JobUpdate>,
+) -> io::Result<()> {
+    let mut archive =
+        ZipArchive::new(BufReader::new(File::open(archive_path)?)).map_err(io::Error::other)?;
+
+    let _ = updates.send(JobUpdate::Total(archive.len()));
+
+    for index in 0..archive.len() {
+        let result = extract_zip_entry(&mut archive, index, destination);
+
+        if updates.send(JobUpdate::Done(result)).is_err() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+fn extract_zip_entry(
+    archive: &mut ZipArchive<BufReader<File>>,
+    index: usize,
+    destination: &Path,
+) -> Result<(), String> {
+    let mut file = archive.by_index(index).map_err(|e| e.to_string())?;
+
+    // enclosed_name rejects absolute paths and ones escaping through ".."
+    let Some(name) = file.enclosed_name() else {
+        let name = file.name().map(|name| name.to_string()).unwrap_or_default();
+        return Err(format!("{}: Unsafe path, skipped", name));
+    };
+
+    if file.is_symlink() {
+        return Err(format!("{}: Symlinks aren't extracted", name.display()));
+    }
+
+    let output = destination.join(&name);
+    let error = |e: io::Error| format!("{}: {}", output.display(), e);
+
+    if file.is_dir() {
+        return create_dir_all(&output).map_err(error);
+    }
+
+    if let Some(parent) = output.parent() {
+        create_dir_all(parent).map_err(error)?;
+    }
+
+    let mut writer = BufWriter::new(File::create_new(&output).map_err(error)?);
+    io::copy(&mut file, &mut writer).map_err(error)?;
+
+    #[cfg(unix)]
+    if let Some(mode) = file.unix_mode() {
+        use std::os::unix::fs::PermissionsExt;
+        let permissions = std::fs::Permissions::from_mode(mode & 0o777);
+        std::fs::set_permissions(&output, permissions).map_err(error)?;
+    }
+
+    Ok(())
+}
+
+fn extract_tar(
+    archive_path: &Path,
+    kind: ArchiveKind,
+    destination: &Path,
+    updates: &Sender<JobUpdate>,
+) -> io::Result<()> {
+    let mut archive = open_tar(archive_path, kind)?;
+    archive.set_overwrite(false);
+
+    for entry in archive.entries()? {
+        // unpack_in refuses entries that would land outside of the destination,
+        // including through links unpacked earlier
+        let result = entry
+            .and_then(|mut entry| {
+                let name = entry.path()?.display().to_string();
+
+                match entry.unpack_in(destination) {
+                    Ok(true) => Ok(()),
+                    Ok(false) => Err(io::Error::other(format!("{}: Unsafe path, skipped", name))),
+                    Err(e) => Err(io::Error::other(format!("{}: {}", name, e))),
+                }
+            })
+            .map_err(|e| e.to_string());
+
+        if updates.send(JobUpdate::Done(result)).is_err() {
+            break;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,59 @@
+use crate::gravily::{Config, IgnoreMode};
+
+use std::env::var;
+use std::fs::read_to_string;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            image_name_pattern: String::from("{name}_{action}.{ext}"),
+            columns: Vec::new(),
+            show_hidden: false,
+            ignore_rules: IgnoreMode::Off,
+        }
+    }
+}
+
+impl Config {
+    // A missing config file isn't an error, the defaults are used instead.
+    // `path` replaces the usual location
+    pub fn load(path: Option<&Path>) -> Result<Self, String> {
+        let Some(path) = path
+            .map(Path::to_path_buf)
+            .or_else(|| config_dir().map(|dir| dir.join("config.json")))
+        else {
+            return Ok(Self::default());
+        };
+
+        match read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|e| format!("Error reading config {}: {}", path.display(), e)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("Error reading config {}: {}", path.display(), e)),
+        }
+    }
+}
+
+// Where bookmarks and the like are kept, ~/.local/share/gravily by default
+pub fn data_dir() -> Option<PathBuf> {
+    match var("XDG_DATA_HOME") {
+        Ok(dir) if !dir.is_empty() => Some(PathBuf::from(dir).join("gravily")),
+        _ => var("HOME").ok().map(|home| {
+            PathBuf::from(home)
+                .join(".local")
+                .join("share")
+                .join("gravily")
+        }),
+    }
+}
+
+pub fn config_dir() -> Option<PathBuf> {
+    match var("XDG_CONFIG_HOME") {
+        Ok(dir) if !dir.is_empty() => Some(PathBuf::from(dir).join("gravily")),
+        _ => var("HOME")
+            .ok()
+            .map(|home| PathBuf::from(home).join(".config").join("gravily")),
+    }
+}
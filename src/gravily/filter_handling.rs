@@ -0,0 +1,117 @@
+use super::FileManager;
+use crate::gravily::{Filter, InputMode};
+
+use globset::GlobBuilder;
+
+use std::ffi::OsStr;
+
+impl Filter {
+    // Queries with glob characters are globs, anything else matches as a
+    // substring. Both only care about case once there's an uppercase letter
+    pub fn new(query: &str) -> Result<Self, String> {
+        let case_sensitive = query.chars().any(char::is_uppercase);
+
+        if !query.contains(['*', '?', '[', '{']) {
+            return Ok(Self {
+                query: query.to_string(),
+                glob: None,
+                case_sensitive,
+            });
+        }
+
+        let glob = GlobBuilder::new(query)
+            .case_insensitive(!case_sensitive)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| format!("Invalid filter {:?}: {}", query, e))?;
+
+        Ok(Self {
+            query: query.to_string(),
+            glob: Some(glob.compile_matcher()),
+            case_sensitive,
+        })
+    }
+
+    pub fn is_match(&self, file_name: &OsStr) -> bool {
+        if let Some(glob) = &self.glob {
+            return glob.is_match(file_name);
+        }
+
+        let file_name = file_name.to_string_lossy();
+
+        if self.case_sensitive {
+            file_name.contains(&self.query)
+        } else {
+            file_name.to_lowercase().contains(&self.query)
+        }
+    }
+}
+
+impl FileManager {
+    pub fn current_filter(&self) -> Option<&Filter> {
+        self.filters.get(&self.path)
+    }
+
+    pub fn start_filter(&mut self) {
+        let query = self
+            .current_filter()
+            .map(|filter| filter.query.clone())
+            .unwrap_or_default();
+
+        self.input = query.into();
+        self.input_mode = InputMode::Filter;
+    }
+
+    // Narrows the listing as the filter is typed, an invalid glob keeps the
+    // last one that worked
+    pub fn update_filter(&mut self) {
+        let query = self.input.value().to_string();
+
+        if query.is_empty() {
+            self.reload_keeping_hovered(|file_manager| {
+                file_manager.filters.remove(&file_manager.path);
+            });
+            return;
+        }
+
+        if let Ok(filter) = Filter::new(&query) {
+            self.reload_keeping_hovered(|file_manager| {
+                file_manager
+                    .filters
+                    .insert(file_manager.path.clone(), filter);
+            });
+        }
+    }
+
+    pub fn finish_filter(&mut self) {
+        if let Err(e) = Filter::new(self.input.value()) {
+            self.error = e;
+        }
+
+        self.input.reset();
+        self.input_mode = InputMode::Navigation;
+    }
+
+    pub fn clear_filter(&mut self) {
+        self.input.reset();
+        self.input_mode = InputMode::Navigation;
+
+        self.reload_keeping_hovered(|file_manager| {
+            file_manager.filters.remove(&file_manager.path);
+        });
+    }
+
+    // Marks everything currently listed, entries hidden by the filter are left
+    // alone
+    pub fn mark_all(&mut self) {
+        self.read_path_items();
+
+        let paths: Vec<_> = self
+            .path_items
+            .iter()
+            .map(|file_name| self.path.join(file_name))
+            .collect();
+
+        self.marked.extend(paths);
+    }
+}
@@ -0,0 +1,623 @@
+use super::FileManager;
+use crate::gravily::{
+    Comparison, EntryKind, FindQuery, FindResults, FindTerm, IgnoreMode, InputMode, Predicate,
+    SortEntry,
+};
+
+use globset::GlobBuilder;
+use ignore::WalkBuilder;
+
+use std::fs::Metadata;
+use std::mem;
+use std::path::Path;
+use std::sync::mpsc::{Sender, TryRecvError, channel};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+// The walk stops after this many results
+const MAX_RESULTS: usize = 10_000;
+// Results are sent once there are this many, or every so often while the
+// walk goes through entries that don't match
+const BATCH_SIZE: usize = 256;
+const BATCH_INTERVAL: Duration = Duration::from_millis(100);
+
+const USAGE: &str =
+    "Try size>100M, mtime<7d, type:symlink, perm:+x, owner:root, ext:log or name:*.rs";
+
+impl FindQuery {
+    // Terms are separated by spaces and all have to match, unless there's an
+    // "or" between them. "and" binds tighter, "!" or "not" negates a term
+    pub fn parse(query: &str) -> Result<Self, String> {
+        let mut groups = vec![Vec::new()];
+        let mut negated = false;
+
+        for word in query.split_whitespace() {
+            match word {
+                "and" | "&&" => {}
+                "or" | "||" => groups.push(Vec::new()),
+                "not" | "!" => negated = !negated,
+                _ => {
+                    let (word_negated, word) = match word.strip_prefix('!') {
+                        Some(word) => (true, word),
+                        None => (false, word),
+                    };
+
+                    let predicate = Predicate::parse(word)?;
+                    if let Some(group) = groups.last_mut() {
+                        group.push(FindTerm {
+                            negated: negated != word_negated,
+                            predicate,
+                        });
+                    }
+                    negated = false;
+                }
+            }
+        }
+
+        if groups.iter().any(Vec::is_empty) {
+            return Err(format!("Missing a term next to \"or\". {}", USAGE));
+        }
+
+        Ok(Self { groups })
+    }
+
+    fn matches(&self, path: &Path, metadata: &Metadata, now: SystemTime) -> bool {
+        self.groups.iter().any(|group| {
+            group
+                .iter()
+                .all(|term| term.predicate.matches(path, metadata, now) != term.negated)
+        })
+    }
+}
+
+impl Predicate {
+    fn parse(word: &str) -> Result<Self, String> {
+        if let Some((name, value)) = word.split_once(':') {
+            return match name {
+                "type" => match value {
+                    "f" | "file" => Ok(Predicate::Type(EntryKind::File)),
+                    "d" | "dir" | "directory" => Ok(Predicate::Type(EntryKind::Dir)),
+                    "l" | "link" | "symlink" => Ok(Predicate::Type(EntryKind::Symlink)),
+                    _ => Err(format!(
+                        "Unknown type {:?}, use file, dir or symlink",
+                        value
+                    )),
+                },
+                "perm" => parse_permissions(value),
+                "owner" if !value.is_empty() => Ok(Predicate::Owner(value.to_string())),
+                "ext" if !value.is_empty() => Ok(Predicate::Extension(
+                    value.trim_start_matches('.').to_lowercase(),
+                )),
+                "name" => {
+                    let glob = GlobBuilder::new(value)
+                        .case_insensitive(!value.chars().any(char::is_uppercase))
+                        .build()
+                        .map_err(|e| format!("Invalid name pattern {:?}: {}", value, e))?;
+
+                    Ok(Predicate::Name(glob.compile_matcher()))
+                }
+                _ => Err(format!("Unknown predicate {:?}. {}", word, USAGE)),
+            };
+        }
+
+        let split = word
+            .find(['<', '>', '='])
+            .ok_or_else(|| format!("Unknown predicate {:?}. {}", word, USAGE))?;
+        let (name, rest) = word.split_at(split);
+
+        let (comparison, value) = [
+            ("<=", Comparison::AtMost),
+            (">=", Comparison::AtLeast),
+            ("<", Comparison::Less),
+            (">", Comparison::Greater),
+            ("=", Comparison::Equal),
+        ]
+        .into_iter()
+        .find_map(|(operator, comparison)| {
+            rest.strip_prefix(operator).map(|value| (comparison, value))
+        })
+        .ok_or_else(|| format!("Unknown comparison in {:?}", word))?;
+
+        match name {
+            "size" => Ok(Predicate::Size(comparison, parse_size(value)?)),
+            "mtime" | "age" => Ok(Predicate::Modified(comparison, parse_age(value)?)),
+            _ => Err(format!("Unknown predicate {:?}. {}", word, USAGE)),
+        }
+    }
+
+    fn matches(&self, path: &Path, metadata: &Metadata, now: SystemTime) -> bool {
+        match self {
+            // directories don't have a size of their own
+            Predicate::Size(comparison, size) => {
+                metadata.is_file() && comparison.holds(metadata.len(), *size)
+            }
+            Predicate::Modified(comparison, age) => metadata.modified().is_ok_and(|modified| {
+                comparison.holds(now.duration_since(modified).unwrap_or_default(), *age)
+            }),
+            Predicate::Type(EntryKind::File) => metadata.is_file(),
+            Predicate::Type(EntryKind::Dir) => metadata.is_dir(),
+            Predicate::Type(EntryKind::Symlink) => metadata.is_symlink(),
+            Predicate::Permissions(check, bits) => permissions_match(metadata, *check, *bits),
+            Predicate::Owner(owner) => owner_matches(metadata, owner),
+            Predicate::Extension(extension) => path.file_name().is_some_and(|file_name| {
+                file_name
+                    .to_string_lossy()
+                    .to_lowercase()
+                    .ends_with(&format!(".{}", extension))
+            }),
+            Predicate::Name(glob) => path
+                .file_name()
+                .is_some_and(|file_name| glob.is_match(file_name)),
+        }
+    }
+}
+
+impl Comparison {
+    fn holds<T: Ord>(self, value: T, limit: T) -> bool {
+        match self {
+            Comparison::Less => value < limit,
+            Comparison::AtMost => value <= limit,
+            Comparison::Equal => value == limit,
+            Comparison::AtLeast => value >= limit,
+            Comparison::Greater => value > limit,
+        }
+    }
+}
+
+impl FileManager {
+    pub fn is_showing_find_results(&self) -> bool {
+        self.find_results
+            .as_ref()
+            .is_some_and(|results| results.root == self.path)
+    }
+
+    pub fn start_find_input(&mut self) {
+        if self.is_in_archive() {
+            self.error = String::from("Can't find entries inside archives");
+            return;
+        }
+
+        self.input.reset();
+        self.input_mode = InputMode::Find;
+    }
+
+    // Walks everything below the current directory on another thread, the
+    // matches are listed in place of the directory's own entries
+    pub fn start_find(&mut self, query: &str) {
+        let query = query.trim();
+        if query.is_empty() {
+            return;
+        }
+
+        let find_query = match FindQuery::parse(query) {
+            Ok(find_query) => find_query,
+            Err(e) => {
+                self.error = e;
+                return;
+            }
+        };
+
+        if self.is_in_archive() {
+            self.error = String::from("Can't find entries inside archives");
+            return;
+        }
+
+        let (sender, walker) = channel();
+        let root = self.path.clone();
+        let show_hidden = self.show_hidden;
+        let use_ignore_files = self.ignore_mode == IgnoreMode::Hide;
+        thread::spawn(move || {
+            walk_matches(&root, &find_query, show_hidden, use_ignore_files, sender)
+        });
+
+        self.find_results = Some(FindResults {
+            root: self.path.clone(),
+            query: query.to_string(),
+            entries: Vec::new(),
+            walker: Some(walker),
+        });
+        self.state.select_first();
+    }
+
+    pub fn poll_find(&mut self) {
+        let Some(results) = &mut self.find_results else {
+            return;
+        };
+        let Some(walker) = &results.walker else {
+            return;
+        };
+
+        loop {
+            match walker.try_recv() {
+                Ok(batch) => {
+                    results.entries.extend(batch);
+
+                    // the listing was empty when the find started
+                    if self.state.selected().is_none() {
+                        self.state.select_first();
+                    }
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    results.walker = None;
+                    break;
+                }
+            }
+        }
+    }
+
+    // Dropping the receiver stops a walk that's still going
+    pub fn close_find_results(&mut self) {
+        self.find_results = None;
+        self.state.select_first();
+    }
+
+    // Forgets results that were deleted or moved away
+    pub fn prune_find_results(&mut self) {
+        if let Some(results) = &mut self.find_results {
+            let root = &results.root;
+            results
+                .entries
+                .retain(|entry| root.join(&entry.name).symlink_metadata().is_ok());
+        }
+    }
+
+    // Lists the results by their path below the root, so every operation that
+    // joins `path` with a listed name works on them as is
+    pub fn read_find_items(&mut self) {
+        let Some(results) = &self.find_results else {
+            return;
+        };
+
+        let mut entries: Vec<SortEntry> = results
+            .entries
+            .iter()
+            .filter(|entry| self.is_shown(&entry.name, entry.is_dir))
+            .cloned()
+            .collect();
+
+        self.sort.sort(&mut entries);
+        self.ignored_items.clear();
+        self.path_items = entries.into_iter().map(|entry| entry.name).collect();
+    }
+
+    pub fn find_description(&self) -> String {
+        let Some(results) = &self.find_results else {
+            return String::new();
+        };
+
+        let mut description = format!("{}: {} found", results.query, results.entries.len());
+        if results.walker.is_some() {
+            description.push_str(", searching...");
+        } else if results.entries.len() >= MAX_RESULTS {
+            description.push_str(", stopped early");
+        }
+
+        description
+    }
+}
+
+// Runs on its own thread and gives up once the results are closed
+fn walk_matches(
+    root: &Path,
+    query: &FindQuery,
+    show_hidden: bool,
+    use_ignore_files: bool,
+    sender: Sender<Vec<SortEntry>>,
+) {
+    let walk = WalkBuilder::new(root)
+        .standard_filters(use_ignore_files)
+        .hidden(!show_hidden)
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .build();
+
+    let now = SystemTime::now();
+    let mut batch = Vec::new();
+    let mut last_sent = Instant::now();
+    let mut found = 0;
+
+    for entry in walk
+        .filter_map(Result::ok)
+        .filter(|entry| entry.depth() > 0)
+    {
+        if let Ok(metadata) = entry.metadata()
+            && query.matches(entry.path(), &metadata, now)
+            && let Ok(relative_path) = entry.path().strip_prefix(root)
+        {
+            batch.push(SortEntry::from_metadata(
+                relative_path.as_os_str().to_os_string(),
+                entry.path(),
+                &metadata,
+            ));
+            found += 1;
+        }
+
+        if !batch.is_empty() && (batch.len() == BATCH_SIZE || last_sent.elapsed() >= BATCH_INTERVAL)
+        {
+            if sender.send(mem::take(&mut batch)).is_err() {
+                return;
+            }
+            last_sent = Instant::now();
+        }
+
+        if found == MAX_RESULTS {
+            break;
+        }
+    }
+
+    let _ = sender.send(batch);
+}
+
+// "100M", "1.5G", "20KiB"... in powers of 1024, plain numbers are bytes
+fn parse_size(value: &str) -> Result<u64, String> {
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("Invalid size {:?}", value))?;
+
+    let power = match unit
+        .to_lowercase()
+        .trim_end_matches("ib")
+        .trim_end_matches('b')
+    {
+        "" => 0,
+        "k" => 1,
+        "m" => 2,
+        "g" => 3,
+        "t" => 4,
+        _ => {
+            return Err(format!(
+                "Unknown size unit in {:?}, use K, M, G or T",
+                value
+            ));
+        }
+    };
+
+    Ok((number * 1024_f64.powi(power)) as u64)
+}
+
+// "30m", "12h", "7d", "2w"... plain numbers are days
+fn parse_age(value: &str) -> Result<Duration, String> {
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("Invalid age {:?}", value))?;
+
+    let seconds = match unit {
+        "s" => 1,
+        "m" | "min" => 60,
+        "h" => 60 * 60,
+        "" | "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        "y" => 365 * 24 * 60 * 60,
+        _ => {
+            return Err(format!(
+                "Unknown age unit in {:?}, use s, m, h, d, w or y",
+                value
+            ));
+        }
+    };
+
+    Duration::try_from_secs_f64(number * seconds as f64)
+        .map_err(|_| format!("Age {:?} is too long", value))
+}
+
+// "+x" has any of the bits, "-w" none of them, "644" exactly those
+// permissions. Octal bits work with "+" and "-" too, like "-022"
+fn parse_permissions(value: &str) -> Result<Predicate, String> {
+    let (check, bits) = match value.chars().next() {
+        Some(check @ ('+' | '-')) => (check, &value[1..]),
+        _ => ('=', value),
+    };
+
+    if !bits.is_empty() && bits.chars().all(|c| c.is_digit(8)) {
+        let mode = u32::from_str_radix(bits, 8).unwrap_or(u32::MAX);
+
+        // exact modes need all three digits, and "+0" would match nothing
+        let is_valid = mode <= 0o7777
+            && if check == '=' {
+                bits.len() >= 3
+            } else {
+                mode != 0
+            };
+        if !is_valid {
+            return Err(format!(
+                "Invalid permissions {:?}, use +x, -w or 644",
+                value
+            ));
+        }
+
+        return Ok(Predicate::Permissions(check, mode));
+    }
+
+    if check == '=' {
+        return Err(format!(
+            "Invalid permissions {:?}, use +x, -w or 644",
+            value
+        ));
+    }
+
+    let mut mode = 0;
+    for c in bits.chars() {
+        mode |= match c {
+            'r' => 0o444,
+            'w' => 0o222,
+            'x' => 0o111,
+            _ => return Err(format!("Invalid permissions {:?}, use r, w and x", value)),
+        };
+    }
+
+    if mode == 0 {
+        return Err(format!(
+            "Invalid permissions {:?}, use +x, -w or 644",
+            value
+        ));
+    }
+
+    Ok(Predicate::Permissions(check, mode))
+}
+
+#[cfg(unix)]
+fn permissions_match(metadata: &Metadata, check: char, bits: u32) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = metadata.permissions().mode() & 0o7777;
+
+    match check {
+        '+' => mode & bits != 0,
+        '-' => mode & bits == 0,
+        _ => mode == bits,
+    }
+}
+
+// Only the read-only flag exists, so "+w" and "-w" are all that can match
+#[cfg(not(unix))]
+fn permissions_match(metadata: &Metadata, check: char, bits: u32) -> bool {
+    let writable = !metadata.permissions().readonly();
+
+    match check {
+        '+' if bits & 0o222 != 0 => writable,
+        '-' if bits & 0o222 != 0 => !writable,
+        _ => false,
+    }
+}
+
+#[cfg(unix)]
+fn owner_matches(metadata: &Metadata, owner: &str) -> bool {
+    use crate::gravily::column_handling::user_name;
+    use std::os::unix::fs::MetadataExt;
+
+    metadata.uid().to_string() == owner
+        || user_name(metadata.uid()).is_some_and(|name| name == owner)
+}
+
+#[cfg(not(unix))]
+fn owner_matches(_metadata: &Metadata, _owner: &str) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FindQuery, parse_age};
+    use crate::gravily::{Comparison, EntryKind, Predicate};
+
+    use std::time::Duration;
+
+    fn terms(query: &str) -> Vec<Vec<(bool, Predicate)>> {
+        FindQuery::parse(query)
+            .unwrap()
+            .groups
+            .into_iter()
+            .map(|group| {
+                group
+                    .into_iter()
+                    .map(|term| (term.negated, term.predicate))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parses_ages_in_every_unit() {
+        assert_eq!(parse_age("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_age("30m"), Ok(Duration::from_secs(30 * 60)));
+        assert_eq!(parse_age("1.5h"), Ok(Duration::from_secs(90 * 60)));
+        assert_eq!(parse_age("7"), Ok(Duration::from_secs(7 * 24 * 60 * 60)));
+        assert_eq!(parse_age("2w"), Ok(Duration::from_secs(14 * 24 * 60 * 60)));
+    }
+
+    #[test]
+    fn rejects_bad_ages() {
+        assert!(parse_age("").is_err());
+        assert!(parse_age("7x").is_err());
+        assert!(parse_age("d").is_err());
+        assert!(parse_age("999999999999y").is_err());
+    }
+
+    #[test]
+    fn terms_are_anded_within_a_group() {
+        let groups = terms("size>100M mtime<7d");
+
+        assert_eq!(groups.len(), 1);
+        assert!(matches!(
+            groups[0][..],
+            [
+                (false, Predicate::Size(Comparison::Greater, 104_857_600)),
+                (false, Predicate::Modified(Comparison::Less, _)),
+            ]
+        ));
+    }
+
+    #[test]
+    fn or_starts_a_new_group() {
+        let groups = terms("type:symlink or perm:+x and ext:log");
+
+        assert!(matches!(
+            groups[0][..],
+            [(false, Predicate::Type(EntryKind::Symlink))]
+        ));
+        assert!(matches!(
+            groups[1][..],
+            [
+                (false, Predicate::Permissions('+', 0o111)),
+                (false, Predicate::Extension(_)),
+            ]
+        ));
+    }
+
+    #[test]
+    fn negations_apply_to_the_next_term() {
+        let groups = terms("!owner:root not ext:log not not name:*.rs");
+
+        assert!(matches!(
+            groups[0][..],
+            [
+                (true, Predicate::Owner(_)),
+                (true, Predicate::Extension(_)),
+                (false, Predicate::Name(_)),
+            ]
+        ));
+    }
+
+    #[test]
+    fn parses_octal_permissions() {
+        assert!(matches!(
+            terms("perm:644")[0][..],
+            [(false, Predicate::Permissions('=', 0o644))]
+        ));
+        assert!(matches!(
+            terms("perm:+755")[0][..],
+            [(false, Predicate::Permissions('+', 0o755))]
+        ));
+        assert!(matches!(
+            terms("perm:-022")[0][..],
+            [(false, Predicate::Permissions('-', 0o022))]
+        ));
+    }
+
+    #[test]
+    fn rejects_bad_queries() {
+        for query in [
+            "or size>1",
+            "size>1 or",
+            "size~1",
+            "bogus",
+            "type:pipe",
+            "perm:64",
+            "perm:+0",
+            "perm:99999",
+            "perm:+q",
+            "mtime<999999999999y",
+        ] {
+            assert!(FindQuery::parse(query).is_err(), "{:?} was accepted", query);
+        }
+    }
+}
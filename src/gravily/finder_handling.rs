@@ -0,0 +1,387 @@
+use super::FileManager;
+use crate::gravily::helper_functions::scroll_offset;
+use crate::gravily::{Finder, FinderMatch, InputMode};
+
+use ignore::WalkBuilder;
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style, Stylize},
+    symbols::border,
+    text::{Line, Span},
+    widgets::{Block, List, ListState, Paragraph, StatefulWidget, Widget},
+};
+
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Sender, TryRecvError, channel};
+use std::thread;
+
+// The walk stops here, so a huge tree can't take all the memory
+const MAX_CANDIDATES: usize = 200_000;
+// Paths are sent in batches so the walk never waits on the screen
+const BATCH_SIZE: usize = 512;
+
+impl Finder {
+    // Scores the candidates from `start` on and keeps the matches best first,
+    // the highlighted one stays highlighted while more stream in
+    fn add_matches(&mut self, start: usize, query: &str) {
+        let highlighted = self
+            .state
+            .selected()
+            .and_then(|selected| self.matches.get(selected))
+            .map(|found| found.index);
+
+        let case_sensitive = query.chars().any(char::is_uppercase);
+
+        self.matches
+            .extend(self.candidates[start..].iter().enumerate().filter_map(
+                |(offset, candidate)| {
+                    let (score, positions) =
+                        fuzzy_match(&candidate.to_string_lossy(), query, case_sensitive)?;
+
+                    Some(FinderMatch {
+                        index: start + offset,
+                        score,
+                        positions,
+                    })
+                },
+            ));
+
+        // shorter paths win ties, then the order they were found in
+        let candidates = &self.candidates;
+        self.matches.sort_by(|a, b| {
+            b.score
+                .cmp(&a.score)
+                .then_with(|| {
+                    let a_length = candidates[a.index].as_os_str().len();
+                    a_length.cmp(&candidates[b.index].as_os_str().len())
+                })
+                .then_with(|| a.index.cmp(&b.index))
+        });
+
+        let position = highlighted
+            .and_then(|index| self.matches.iter().position(|found| found.index == index))
+            .unwrap_or(0);
+        self.state.select(Some(position));
+    }
+
+    fn highlighted_path(&self) -> Option<PathBuf> {
+        let found = self.matches.get(self.state.selected()?)?;
+        Some(self.root.join(&self.candidates[found.index]))
+    }
+}
+
+impl FileManager {
+    pub fn is_finder_open(&self) -> bool {
+        self.finder.is_some()
+    }
+
+    pub fn start_finder(&mut self) {
+        if self.is_in_archive() {
+            self.error = String::from("Can't search for files inside archives");
+            return;
+        }
+
+        let (sender, walker) = channel();
+        let root = self.path.clone();
+        let show_hidden = self.show_hidden;
+        thread::spawn(move || walk_candidates(&root, show_hidden, sender));
+
+        self.finder = Some(Finder {
+            root: self.path.clone(),
+            candidates: Vec::new(),
+            matches: Vec::new(),
+            walker: Some(walker),
+            state: ListState::default(),
+        });
+        self.input.reset();
+        self.input_mode = InputMode::Finder;
+    }
+
+    // Picks up whatever the walk found since the last frame
+    pub fn poll_finder(&mut self) {
+        let Some(finder) = &mut self.finder else {
+            return;
+        };
+        let Some(walker) = &finder.walker else {
+            return;
+        };
+
+        let start = finder.candidates.len();
+        loop {
+            match walker.try_recv() {
+                Ok(batch) => finder.candidates.extend(batch),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    finder.walker = None;
+                    break;
+                }
+            }
+        }
+
+        if finder.candidates.len() > start {
+            finder.add_matches(start, self.input.value());
+        }
+    }
+
+    pub fn update_finder(&mut self) {
+        if let Some(finder) = &mut self.finder {
+            finder.matches.clear();
+            finder.state.select(None);
+            finder.add_matches(0, self.input.value());
+        }
+    }
+
+    pub fn move_finder_selection(&mut self, down: bool) {
+        if let Some(finder) = &mut self.finder {
+            if down {
+                finder.state.select_next();
+            } else {
+                finder.state.select_previous();
+            }
+        }
+    }
+
+    // Dropping the finder also drops the receiver, which stops the walk
+    pub fn close_finder(&mut self) {
+        self.finder = None;
+        self.input.reset();
+        self.input_mode = InputMode::Navigation;
+    }
+
+    pub fn open_finder_match(&mut self) {
+        let highlighted = self.finder.as_ref().and_then(Finder::highlighted_path);
+        self.close_finder();
+
+        if let Some(path) = highlighted {
+            self.reveal_path(&path);
+        }
+    }
+
+    pub fn render_finder(&mut self, area: Rect, buf: &mut Buffer) {
+        let Some(finder) = &mut self.finder else {
+            return;
+        };
+
+        let [list_area, preview_area] =
+            Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .areas(area);
+
+        let mut count = format!(" {}/{} ", finder.matches.len(), finder.candidates.len());
+        if finder.walker.is_some() {
+            count.push_str("walking... ");
+        } else if finder.candidates.len() >= MAX_CANDIDATES {
+            count.push_str("(stopped early) ");
+        }
+
+        let block = Block::bordered()
+            .title(Line::from(vec![
+                " Find in ".into(),
+                finder.root.display().to_string().yellow(),
+                " ".into(),
+            ]))
+            .title_bottom(Line::from(count).right_aligned())
+            .border_set(border::ROUNDED);
+
+        // only the rows on screen are built, there can be a lot of matches
+        let visible_rows = block.inner(list_area).height as usize;
+        let selected = finder
+            .state
+            .selected()
+            .map(|selected| selected.min(finder.matches.len().saturating_sub(1)));
+
+        let offset = scroll_offset(finder.state.offset(), selected, visible_rows);
+
+        let items: Vec<Line> = finder
+            .matches
+            .iter()
+            .skip(offset)
+            .take(visible_rows)
+            .map(|found| {
+                highlight_positions(
+                    &finder.candidates[found.index].to_string_lossy(),
+                    &found.positions,
+                )
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_symbol("> ")
+            .highlight_spacing(ratatui::widgets::HighlightSpacing::Always);
+
+        let mut list_state = ListState::default().with_selected(selected.map(|s| s - offset));
+        StatefulWidget::render(list, list_area, buf, &mut list_state);
+
+        finder.state.select(selected);
+        *finder.state.offset_mut() = offset;
+
+        let Some(path) = finder.highlighted_path() else {
+            Block::bordered()
+                .border_set(border::ROUNDED)
+                .render(preview_area, buf);
+            return;
+        };
+
+        let block = Block::bordered()
+            .title(Line::from(vec![
+                " ".into(),
+                path.display().to_string().yellow(),
+                " ".into(),
+            ]))
+            .border_set(border::ROUNDED);
+
+        if path.is_dir() {
+            self.render_dir_preview(&path, block, preview_area, buf);
+        } else if path.is_file() {
+            self.render_file_preview(&path, block, preview_area, buf);
+        } else {
+            Paragraph::new("Not a regular file")
+                .block(block)
+                .render(preview_area, buf);
+        }
+    }
+}
+
+// Runs on its own thread and gives up as soon as the finder is closed and
+// nobody is listening anymore
+fn walk_candidates(root: &Path, show_hidden: bool, sender: Sender<Vec<PathBuf>>) {
+    let walk = WalkBuilder::new(root)
+        .hidden(!show_hidden)
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .build();
+
+    let mut batch = Vec::new();
+
+    for (count, entry) in walk
+        .filter_map(Result::ok)
+        .filter(|entry| entry.depth() > 0)
+        .enumerate()
+    {
+        if count == MAX_CANDIDATES {
+            break;
+        }
+
+        if let Ok(relative_path) = entry.path().strip_prefix(root) {
+            batch.push(relative_path.to_path_buf());
+        }
+
+        if batch.len() == BATCH_SIZE && sender.send(mem::take(&mut batch)).is_err() {
+            return;
+        }
+    }
+
+    let _ = sender.send(batch);
+}
+
+// Every query character has to show up in order. Matches towards the end are
+// preferred, so the file name wins over its directories, and they score more
+// at the start of a word, in a run or inside the file name. Smart case like
+// the search
+pub fn fuzzy_match(text: &str, query: &str, case_sensitive: bool) -> Option<(i64, Vec<usize>)> {
+    let fold = |c: char| {
+        if case_sensitive {
+            c
+        } else {
+            c.to_lowercase().next().unwrap_or(c)
+        }
+    };
+
+    let text: Vec<char> = text.chars().collect();
+    let query: Vec<char> = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(fold)
+        .collect();
+
+    if query.is_empty() {
+        return Some((0, Vec::new()));
+    }
+
+    // the latest place the whole query fits...
+    let mut remaining = query.len();
+    let mut start = None;
+    for (position, &c) in text.iter().enumerate().rev() {
+        if fold(c) == query[remaining - 1] {
+            remaining -= 1;
+            if remaining == 0 {
+                start = Some(position);
+                break;
+            }
+        }
+    }
+
+    // ...tightened by matching forward again from where it begins
+    let mut positions = Vec::with_capacity(query.len());
+    for (position, &c) in text.iter().enumerate().skip(start?) {
+        if positions.len() < query.len() && fold(c) == query[positions.len()] {
+            positions.push(position);
+        }
+    }
+
+    let name_start = text
+        .iter()
+        .rposition(|&c| c == '/')
+        .map_or(0, |position| position + 1);
+
+    let mut score = 0;
+    for (n, &position) in positions.iter().enumerate() {
+        score += 16;
+
+        let word_start = position == 0
+            || matches!(text[position - 1], '/' | '_' | '-' | '.' | ' ')
+            || (text[position - 1].is_lowercase() && text[position].is_uppercase());
+        if word_start {
+            score += 10;
+        }
+
+        if position >= name_start {
+            score += 8;
+        }
+
+        if n > 0 {
+            match position - positions[n - 1] - 1 {
+                0 => score += 12,
+                gap => score -= gap.min(10) as i64,
+            }
+        }
+    }
+
+    Some((score, positions))
+}
+
+// Runs of matched characters are highlighted like search matches
+pub fn highlight_positions(text: &str, positions: &[usize]) -> Line<'static> {
+    let highlight = Style::default().fg(Color::Black).bg(Color::Yellow).bold();
+
+    let mut spans = Vec::new();
+    let mut run = String::new();
+    let mut run_matched = false;
+
+    for (position, c) in text.chars().enumerate() {
+        let matched = positions.binary_search(&position).is_ok();
+
+        if matched != run_matched && !run.is_empty() {
+            let style = if run_matched {
+                highlight
+            } else {
+                Style::default()
+            };
+            spans.push(Span::styled(mem::take(&mut run), style));
+        }
+
+        run.push(c);
+        run_matched = matched;
+    }
+
+    let style = if run_matched {
+        highlight
+    } else {
+        Style::default()
+    };
+    spans.push(Span::styled(run, style));
+
+    Line::from(spans)
+}
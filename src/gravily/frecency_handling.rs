@@ -0,0 +1,263 @@
+use super::FileManager;
+use crate::gravily::config::data_dir;
+use crate::gravily::helper_functions::{escape_field, path_bytes, path_from_bytes, unescape_field};
+use crate::gravily::{Frecency, InputMode};
+
+use std::fs::{create_dir_all, read_to_string, write};
+use std::io::{self, ErrorKind};
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+
+// Once the ranks add up to more than this they all decay, and directories
+// that haven't been visited in a long time drop out
+const MAX_TOTAL_RANK: f64 = 10_000.0;
+
+impl Frecency {
+    // rank<TAB>last access<TAB>path lines, the path escaped like bookmarks
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.splitn(3, '\t');
+        let rank = fields.next()?.parse().ok()?;
+        let last_access = fields.next()?.parse().ok()?;
+        let path = path_from_bytes(unescape_field(fields.next()?));
+
+        Some(Self {
+            path,
+            rank,
+            last_access,
+        })
+    }
+
+    fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{}",
+            self.rank,
+            self.last_access,
+            escape_field(&path_bytes(&self.path))
+        )
+    }
+
+    // Visits count for more the more recent the last one was, like zoxide
+    fn score(&self, now: u64) -> f64 {
+        let weight = match now.saturating_sub(self.last_access) {
+            age if age < HOUR => 4.0,
+            age if age < DAY => 2.0,
+            age if age < WEEK => 0.5,
+            _ => 0.25,
+        };
+
+        self.rank * weight
+    }
+}
+
+impl FileManager {
+    pub fn load_frecency(&mut self) {
+        let Some(path) = data_dir().map(|dir| dir.join("frecency")) else {
+            return;
+        };
+
+        match read_frecency(&path) {
+            Ok(entries) => self.frecency = entries,
+            Err(e) => self.error = format!("Error reading frecency {}: {}", path.display(), e),
+        }
+    }
+
+    // Written on the way out rather than on every visit. The file is read
+    // again first and this session's visits are added to it, so visits other
+    // instances saved in the meantime are kept
+    pub fn save_frecency(&mut self) {
+        let Some(dir) = data_dir() else {
+            self.error = String::from("Error saving frecency: No HOME or XDG_DATA_HOME");
+            return;
+        };
+        let path = dir.join("frecency");
+
+        let mut entries = match read_frecency(&path) {
+            Ok(entries) => entries,
+            Err(e) => {
+                self.error = format!("Error reading frecency {}: {}", path.display(), e);
+                return;
+            }
+        };
+        for visit in &self.frecency_visits {
+            add_rank(&mut entries, visit);
+        }
+        decay(&mut entries);
+
+        let contents: String = entries.iter().map(|entry| entry.to_line() + "\n").collect();
+
+        match create_dir_all(&dir).and_then(|_| write(&path, contents)) {
+            Ok(()) => {
+                self.frecency = entries;
+                self.frecency_visits.clear();
+            }
+            Err(e) => self.error = format!("Error saving frecency {}: {}", dir.display(), e),
+        }
+    }
+
+    pub fn record_visit(&mut self, path: &Path) {
+        self.add_frecency(Frecency {
+            path: path.to_path_buf(),
+            rank: 1.0,
+            last_access: unix_now(),
+        });
+    }
+
+    fn add_frecency(&mut self, visit: Frecency) {
+        add_rank(&mut self.frecency, &visit);
+        decay(&mut self.frecency);
+        add_rank(&mut self.frecency_visits, &visit);
+    }
+
+    pub fn start_jump_input(&mut self) {
+        self.input.reset();
+        self.input_mode = InputMode::Jump;
+    }
+
+    // The highest scoring visited directory matching every fragment of
+    // `query`, other than the current one
+    pub fn best_jump(&self, query: &str) -> Option<&Path> {
+        let keywords: Vec<&str> = query.split_whitespace().collect();
+        let now = unix_now();
+
+        self.frecency
+            .iter()
+            .filter(|entry| entry.path != self.path && matches_keywords(&entry.path, &keywords))
+            .filter(|entry| entry.path.is_dir())
+            .max_by(|a, b| a.score(now).total_cmp(&b.score(now)))
+            .map(|entry| entry.path.as_path())
+    }
+
+    pub fn jump(&mut self, query: &str) {
+        self.input_mode = InputMode::Navigation;
+
+        match self.best_jump(query).map(Path::to_path_buf) {
+            Some(path) => self.go_to(path),
+            None => self.error = format!("No visited directory matches {:?}", query),
+        }
+    }
+
+    // Adds zoxide's scores on top of ours, through its own query command so
+    // its database format doesn't matter
+    pub fn import_zoxide(&mut self) -> Result<(), String> {
+        let output = Command::new("zoxide")
+            .args(["query", "--list", "--score"])
+            .output()
+            .map_err(|e| format!("Couldn't run zoxide: {}", e))?;
+
+        if !output.status.success() {
+            return Err(format!(
+                "zoxide failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        let now = unix_now();
+        let mut imported = 0;
+
+        // the paths are kept as bytes, they don't have to be UTF-8
+        for line in output.stdout.split(|&byte| byte == b'\n') {
+            let line = line.trim_ascii_start();
+            let Some(space) = line.iter().position(|&byte| byte == b' ') else {
+                continue;
+            };
+            let Some(score) = str::from_utf8(&line[..space])
+                .ok()
+                .and_then(|score| score.parse::<f64>().ok())
+            else {
+                continue;
+            };
+            self.add_frecency(Frecency {
+                path: path_from_bytes(line[space..].trim_ascii_start().to_vec()),
+                rank: score,
+                last_access: now,
+            });
+            imported += 1;
+        }
+
+        self.error = format!("Imported {} directories from zoxide", imported);
+        self.save_frecency();
+        Ok(())
+    }
+}
+
+// A missing file just means nothing was visited yet
+fn read_frecency(path: &Path) -> io::Result<Vec<Frecency>> {
+    match read_to_string(path) {
+        Ok(contents) => Ok(contents.lines().filter_map(Frecency::parse).collect()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+fn add_rank(entries: &mut Vec<Frecency>, visit: &Frecency) {
+    match entries.iter_mut().find(|entry| entry.path == visit.path) {
+        Some(entry) => {
+            entry.rank += visit.rank;
+            entry.last_access = entry.last_access.max(visit.last_access);
+        }
+        None => entries.push(Frecency {
+            path: visit.path.clone(),
+            rank: visit.rank,
+            last_access: visit.last_access,
+        }),
+    }
+}
+
+fn decay(entries: &mut Vec<Frecency>) {
+    if entries.iter().map(|entry| entry.rank).sum::<f64>() > MAX_TOTAL_RANK {
+        for entry in entries.iter_mut() {
+            entry.rank *= 0.9;
+        }
+        entries.retain(|entry| entry.rank >= 1.0);
+    }
+}
+
+// Every keyword has to show up in the path in order, and the last one in its
+// final component like zoxide does. Smart case like the search
+fn matches_keywords(path: &Path, keywords: &[&str]) -> bool {
+    let case_sensitive = keywords
+        .iter()
+        .any(|keyword| keyword.chars().any(char::is_uppercase));
+    let fold = |text: &str| {
+        if case_sensitive {
+            text.to_string()
+        } else {
+            text.to_lowercase()
+        }
+    };
+
+    let Some(last) = keywords.last() else {
+        return true;
+    };
+
+    let name = path
+        .file_name()
+        .map(|name| fold(&name.to_string_lossy()))
+        .unwrap_or_default();
+    if !name.contains(&fold(last)) {
+        return false;
+    }
+
+    let text = fold(&path.to_string_lossy());
+    let mut rest = text.as_str();
+    for keyword in keywords {
+        let keyword = fold(keyword);
+        match rest.find(&keyword) {
+            Some(index) => rest = &rest[index + keyword.len()..],
+            None => return false,
+        }
+    }
+
+    true
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
@@ -0,0 +1,151 @@
+use super::FileManager;
+
+use image::ImageFormat;
+use ratatui::{
+    buffer::Buffer,
+    layout::{Alignment, Rect},
+    style::{Color, Style, Stylize},
+    symbols::border,
+    text::Line,
+    widgets::{Block, Paragraph, Widget},
+};
+
+const TILE_WIDTH: u16 = 24;
+const TILE_HEIGHT: u16 = 12;
+
+impl FileManager {
+    pub fn is_gallery_open(&self) -> bool {
+        self.gallery
+    }
+
+    pub fn toggle_gallery(&mut self) {
+        self.gallery = !self.gallery;
+        self.gallery_scroll = 0;
+    }
+
+    // Indices into path_items of every entry that looks like an image
+    pub fn gallery_items(&self) -> Vec<usize> {
+        self.path_items
+            .iter()
+            .enumerate()
+            .filter(|(_, file_name)| ImageFormat::from_path(file_name).is_ok())
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    pub fn move_gallery_selection(&mut self, columns: isize, rows: isize) {
+        let items = self.gallery_items();
+        if items.is_empty() {
+            return;
+        }
+
+        let current = self
+            .state
+            .selected()
+            .and_then(|selected| items.iter().position(|&item| item == selected))
+            .unwrap_or(0) as isize;
+
+        let target = current + columns + rows * self.gallery_columns.max(1) as isize;
+
+        // moving down into a partially filled last row lands on its last tile
+        let target = if rows > 0 && target >= items.len() as isize {
+            let last_row = (items.len() - 1) / self.gallery_columns.max(1);
+            let current_row = current as usize / self.gallery_columns.max(1);
+
+            if current_row < last_row {
+                items.len() as isize - 1
+            } else {
+                current
+            }
+        } else {
+            target
+        };
+
+        if (0..items.len() as isize).contains(&target) {
+            self.state.select(Some(items[target as usize]));
+        }
+    }
+
+    pub fn render_gallery(&mut self, area: Rect, buf: &mut Buffer) {
+        self.read_path_items();
+
+        let path_text = Line::from(vec![
+            " Gallery: ".into(),
+            self.path.display().to_string().yellow(),
+            " ".into(),
+        ]);
+
+        let block = Block::bordered()
+            .title(path_text)
+            .border_set(border::ROUNDED);
+
+        let inner_area = block.inner(area);
+        block.render(area, buf);
+
+        let items = self.gallery_items();
+        if items.is_empty() {
+            Paragraph::new("No images in this directory")
+                .alignment(Alignment::Center)
+                .render(inner_area, buf);
+            return;
+        }
+
+        let columns = (inner_area.width / TILE_WIDTH).max(1) as usize;
+        let rows = (inner_area.height / TILE_HEIGHT).max(1) as usize;
+        self.gallery_columns = columns;
+
+        let selected = self
+            .state
+            .selected()
+            .and_then(|selected| items.iter().position(|&item| item == selected))
+            .unwrap_or(0);
+        self.state.select(Some(items[selected]));
+
+        // keep the selected tile's row on screen
+        let selected_row = selected / columns;
+        if selected_row < self.gallery_scroll {
+            self.gallery_scroll = selected_row;
+        } else if selected_row >= self.gallery_scroll + rows {
+            self.gallery_scroll = selected_row + 1 - rows;
+        }
+
+        let first_visible = self.gallery_scroll * columns;
+
+        // only the visible tiles are handed to the image loader
+        for (slot, &item) in items
+            .iter()
+            .skip(first_visible)
+            .take(rows * columns)
+            .enumerate()
+        {
+            let tile_area = Rect::new(
+                inner_area.x + (slot % columns) as u16 * TILE_WIDTH,
+                inner_area.y + (slot / columns) as u16 * TILE_HEIGHT,
+                TILE_WIDTH,
+                TILE_HEIGHT,
+            )
+            .intersection(inner_area);
+
+            let file_name = self.path_items[item].to_string_lossy().to_string();
+
+            let mut tile = Block::bordered()
+                .title_bottom(Line::from(file_name).centered())
+                .border_set(border::ROUNDED);
+
+            let image_path = self.path.join(&self.path_items[item]);
+
+            if first_visible + slot == selected {
+                tile = tile.border_style(Style::default().fg(Color::Yellow).bold());
+            } else if self.marked.contains(&image_path) {
+                tile = tile.border_style(Style::default().fg(Color::Blue));
+            }
+
+            let image_area = tile.inner(tile_area);
+            tile.render(tile_area, buf);
+
+            if let Some(Ok(image_path)) = self.resolve_preview_path(&image_path) {
+                self.render_image(&image_path, image_area, buf);
+            }
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use super::FileManager;
+use crate::gravily::{Completion, InputMode};
+
+use ratatui::{
+    Frame,
+    layout::Rect,
+    symbols::border,
+    text::Line,
+    widgets::{Block, Clear, List, ListState},
+};
+use tui_input::Input;
+
+use std::env::var;
+use std::fs::read_dir;
+use std::path::{Component, Path, PathBuf};
+
+// Rows of candidates shown at once, the rest scroll
+const MAX_COMPLETION_ROWS: u16 = 10;
+
+impl FileManager {
+    pub fn start_goto(&mut self) {
+        self.input.reset();
+        self.completion = None;
+        self.input_mode = InputMode::GoTo;
+    }
+
+    pub fn cancel_goto(&mut self) {
+        self.input.reset();
+        self.completion = None;
+        self.input_mode = InputMode::Navigation;
+    }
+
+    // Opens the typed directory, or the directory of a typed file with the
+    // cursor on it
+    pub fn goto_path(&mut self, input: &str) {
+        self.input_mode = InputMode::Navigation;
+        self.completion = None;
+
+        if input.trim().is_empty() {
+            return;
+        }
+
+        let path = match expand_path(input, &self.path) {
+            Ok(path) => path,
+            Err(e) => {
+                self.error = e;
+                return;
+            }
+        };
+
+        if path.is_file() {
+            self.reveal_path(&path);
+        } else {
+            // paths inside an open archive don't exist on disk
+            self.go_to(path);
+        }
+    }
+
+    // Fills in the next (or previous) directory whose name starts with what's
+    // after the last separator, a single candidate is taken right away
+    pub fn complete_goto(&mut self, backward: bool) {
+        if self.completion.is_none() {
+            let input = self.input.value();
+            let base = input.rfind('/').map_or("", |index| &input[..=index]);
+            let prefix = &input[base.len()..];
+
+            let names = match expand_path(base, &self.path) {
+                Ok(dir) => completion_names(&dir, prefix, self.show_hidden),
+                Err(_) => Vec::new(),
+            };
+
+            match names.len() {
+                0 => return,
+                1 => {
+                    self.input = Input::new(format!("{}{}/", base, names[0]));
+                    return;
+                }
+                _ => {}
+            }
+
+            self.completion = Some(Completion {
+                base: base.to_string(),
+                names,
+                state: ListState::default(),
+            });
+        }
+
+        let Some(completion) = &mut self.completion else {
+            return;
+        };
+
+        let count = completion.names.len();
+        let selected = match (completion.state.selected(), backward) {
+            (None, false) => 0,
+            (None, true) => count - 1,
+            (Some(selected), false) => (selected + 1) % count,
+            (Some(selected), true) => (selected + count - 1) % count,
+        };
+        completion.state.select(Some(selected));
+
+        self.input = Input::new(format!(
+            "{}{}/",
+            completion.base, completion.names[selected]
+        ));
+    }
+
+    // Typing starts a fresh completion next time Tab is pressed
+    pub fn reset_completion(&mut self) {
+        self.completion = None;
+    }
+
+    // Candidates pop up above the input box, at the bottom of `area`
+    pub fn render_completion(&mut self, frame: &mut Frame, area: Rect) {
+        let Some(completion) = &mut self.completion else {
+            return;
+        };
+
+        let longest = completion
+            .names
+            .iter()
+            .map(|name| name.chars().count())
+            .max()
+            .unwrap_or(0) as u16;
+
+        // room for the borders, the highlight symbol and the trailing separator
+        let width = (longest + 5).min(area.width);
+        let height = (completion.names.len() as u16)
+            .min(MAX_COMPLETION_ROWS)
+            .saturating_add(2)
+            .min(area.height);
+        let popup_area = Rect::new(
+            area.x + 1,
+            area.y + area.height - height,
+            width.min(area.width.saturating_sub(1)),
+            height,
+        );
+
+        let items: Vec<Line> = completion
+            .names
+            .iter()
+            .map(|name| Line::from(format!("{}/", name)))
+            .collect();
+
+        let list = List::new(items)
+            .block(Block::bordered().border_set(border::ROUNDED))
+            .highlight_symbol("> ");
+
+        frame.render_widget(Clear, popup_area);
+        frame.render_stateful_widget(list, popup_area, &mut completion.state);
+    }
+}
+
+// Expands `~` and environment variables, and resolves relative paths and
+// `..` against `current` without following symlinks
+fn expand_path(input: &str, current: &Path) -> Result<PathBuf, String> {
+    let input = input.trim();
+
+    let mut expanded = String::new();
+    let mut rest = input;
+
+    if rest == "~" || rest.starts_with("~/") {
+        expanded.push_str(&var("HOME").map_err(|_| String::from("HOME isn't set"))?);
+        rest = &rest[1..];
+    }
+
+    while let Some(index) = rest.find('$') {
+        expanded.push_str(&rest[..index]);
+        rest = &rest[index + 1..];
+
+        let (name, after) = match rest.strip_prefix('{') {
+            Some(braced) => {
+                let end = braced
+                    .find('}')
+                    .ok_or_else(|| format!("Unclosed ${{ in {:?}", input))?;
+                (&braced[..end], &braced[end + 1..])
+            }
+            None => {
+                let end = rest
+                    .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                    .unwrap_or(rest.len());
+                (&rest[..end], &rest[end..])
+            }
+        };
+
+        if name.is_empty() {
+            expanded.push('$');
+        } else {
+            expanded.push_str(&var(name).map_err(|_| format!("${} isn't set", name))?);
+        }
+        rest = after;
+    }
+    expanded.push_str(rest);
+
+    let mut path = PathBuf::new();
+    for component in current.join(expanded).components() {
+        match component {
+            Component::ParentDir => {
+                path.pop();
+            }
+            Component::CurDir => {}
+            component => path.push(component),
+        }
+    }
+
+    Ok(path)
+}
+
+// Sorted names of the directories in `dir` starting with `prefix`, dotfiles
+// only when they're shown or asked for
+fn completion_names(dir: &Path, prefix: &str, show_hidden: bool) -> Vec<String> {
+    let Ok(entries) = read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut names: Vec<String> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| name.starts_with(prefix))
+        .filter(|name| show_hidden || prefix.starts_with('.') || !name.starts_with('.'))
+        .collect();
+
+    names.sort();
+    names
+}
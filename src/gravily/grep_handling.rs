@@ -0,0 +1,474 @@
+use super::FileManager;
+use crate::gravily::helper_functions::scroll_offset;
+use crate::gravily::search_handling::{compile_pattern, highlight_pattern};
+use crate::gravily::{Grep, GrepFile, GrepMatch, GrepPreview, GrepRow, InputMode};
+
+use ignore::{WalkBuilder, WalkState};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::Stylize,
+    symbols::border,
+    text::Line,
+    widgets::{Block, List, ListState, Paragraph, StatefulWidget, Widget},
+};
+use regex::Regex;
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{Sender, TryRecvError, channel};
+use std::thread;
+
+// The search stops once it found this many lines
+const MAX_MATCHES: usize = 10_000;
+// Files with a NUL byte in their first few KiB are taken as binary and skipped
+const BINARY_CHECK_SIZE: usize = 8 * 1024;
+// Minified files can have huge lines, only the start of them is kept
+const MAX_LINE_LENGTH: usize = 500;
+// and only this much of each line is read and searched
+const MAX_SEARCHED_LINE: u64 = 64 * 1024;
+// Lines listed before and after each match
+const CONTEXT_LINES: usize = 2;
+
+impl Grep {
+    // Every file is a header row followed by its matches, each between the
+    // lines of context around it
+    fn rows(&self) -> Vec<GrepRow> {
+        let mut rows = Vec::new();
+
+        for (file_index, file) in self.files.iter().enumerate() {
+            rows.push(GrepRow::File(file_index));
+
+            for (match_index, found) in file.matches.iter().enumerate() {
+                let split = found
+                    .context
+                    .partition_point(|(line_number, _)| *line_number < found.line_number);
+                let context = |index| GrepRow::Context(file_index, match_index, index);
+
+                rows.extend((0..split).map(context));
+                rows.push(GrepRow::Match(file_index, match_index));
+                rows.extend((split..found.context.len()).map(context));
+            }
+        }
+
+        rows
+    }
+
+    fn selected_match(&self) -> Option<(PathBuf, &GrepMatch)> {
+        let GrepRow::Match(file_index, match_index) = *self.rows().get(self.state.selected()?)?
+        else {
+            return None;
+        };
+        let file = &self.files[file_index];
+
+        Some((self.root.join(&file.path), file.matches.get(match_index)?))
+    }
+
+    fn match_count(&self) -> usize {
+        self.files.iter().map(|file| file.matches.len()).sum()
+    }
+}
+
+impl FileManager {
+    pub fn is_grep_open(&self) -> bool {
+        self.grep.is_some()
+    }
+
+    pub fn start_grep_input(&mut self) {
+        if self.is_in_archive() {
+            self.error = String::from("Can't search file contents inside archives");
+            return;
+        }
+
+        self.input.reset();
+        self.input_mode = InputMode::Grep;
+    }
+
+    // Searches every file below the current directory on a pool of threads,
+    // skipping what ignore files leave out like the finder does
+    pub fn start_grep(&mut self) {
+        let query = self.input.value_and_reset();
+        self.input_mode = InputMode::Navigation;
+
+        if query.is_empty() {
+            return;
+        }
+
+        let Some(pattern) = compile_pattern(&query, true) else {
+            self.error = format!("Invalid regex {:?}", query);
+            return;
+        };
+
+        let (sender, searcher) = channel();
+        let root = self.path.clone();
+        let search_pattern = pattern.clone();
+        let show_hidden = self.show_hidden;
+        thread::spawn(move || search_files(&root, &search_pattern, show_hidden, sender));
+
+        self.grep = Some(Grep {
+            root: self.path.clone(),
+            pattern,
+            files: Vec::new(),
+            searcher: Some(searcher),
+            state: ListState::default(),
+            preview: None,
+        });
+    }
+
+    pub fn poll_grep(&mut self) {
+        let Some(grep) = &mut self.grep else {
+            return;
+        };
+        let Some(searcher) = &grep.searcher else {
+            return;
+        };
+
+        loop {
+            match searcher.try_recv() {
+                Ok(file) => grep.files.push(file),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    grep.searcher = None;
+                    break;
+                }
+            }
+        }
+
+        // land on the first match rather than its file's header
+        if grep.state.selected().is_none() {
+            let first = grep
+                .rows()
+                .iter()
+                .position(|row| matches!(row, GrepRow::Match(..)));
+            grep.state.select(first);
+        }
+    }
+
+    // Moves between matches, file headers and context are skipped
+    pub fn move_grep_selection(&mut self, down: bool) {
+        let Some(grep) = &mut self.grep else {
+            return;
+        };
+
+        let rows = grep.rows();
+        let current = grep.state.selected().unwrap_or(0);
+        let is_match = |row: &usize| matches!(rows[*row], GrepRow::Match(..));
+
+        let next = if down {
+            (current + 1..rows.len()).find(is_match)
+        } else {
+            (0..current).rev().find(is_match)
+        };
+
+        if next.is_some() {
+            grep.state.select(next);
+        }
+    }
+
+    // Dropping the receiver makes the searching threads stop
+    pub fn close_grep(&mut self) {
+        self.grep = None;
+    }
+
+    pub fn open_grep_match(&mut self, in_editor: bool) {
+        let Some((path, line_number)) = self
+            .grep
+            .as_ref()
+            .and_then(Grep::selected_match)
+            .map(|(path, found)| (path, found.line_number))
+        else {
+            return;
+        };
+
+        if in_editor {
+            self.editor_request = Some((path, line_number));
+        } else {
+            self.close_grep();
+            self.reveal_path(&path);
+        }
+    }
+
+    pub fn render_grep(&mut self, area: Rect, buf: &mut Buffer) {
+        let Some(grep) = &mut self.grep else {
+            return;
+        };
+
+        let [list_area, preview_area] =
+            Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .areas(area);
+
+        let match_count = grep.match_count();
+        let mut count = format!(" {} matches in {} files ", match_count, grep.files.len());
+        if grep.searcher.is_some() {
+            count.push_str("searching... ");
+        } else if match_count >= MAX_MATCHES {
+            count.push_str("(stopped early) ");
+        }
+
+        let block = Block::bordered()
+            .title(Line::from(vec![
+                " Contents matching ".into(),
+                grep.pattern.as_str().to_string().yellow(),
+                " in ".into(),
+                grep.root.display().to_string().yellow(),
+                " ".into(),
+            ]))
+            .title_bottom(Line::from(count).right_aligned())
+            .border_set(border::ROUNDED);
+
+        let rows = grep.rows();
+
+        // same scrolling the main listing does, only the rows on screen are built
+        let visible_rows = block.inner(list_area).height as usize;
+        let selected = grep.state.selected();
+        let offset = scroll_offset(grep.state.offset(), selected, visible_rows);
+
+        let items: Vec<Line> = rows
+            .iter()
+            .skip(offset)
+            .take(visible_rows)
+            .map(|&row| match row {
+                GrepRow::File(file_index) => {
+                    let file = &grep.files[file_index];
+
+                    Line::from(vec![
+                        file.path.display().to_string().yellow().bold(),
+                        format!(" ({})", file.matches.len()).dark_gray(),
+                    ])
+                }
+                GrepRow::Match(file_index, match_index) => {
+                    let found = &grep.files[file_index].matches[match_index];
+
+                    let mut spans = vec![format!("{:>5}: ", found.line_number).dark_gray()];
+                    spans.extend(highlight_pattern(&grep.pattern, &found.line).spans);
+                    Line::from(spans)
+                }
+                // marked with '-' instead of ':' like grep does
+                GrepRow::Context(file_index, match_index, index) => {
+                    let (line_number, line) =
+                        &grep.files[file_index].matches[match_index].context[index];
+
+                    Line::from(format!("{:>5}- {}", line_number, line).dark_gray())
+                }
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_symbol("> ")
+            .highlight_spacing(ratatui::widgets::HighlightSpacing::Always);
+
+        let mut list_state =
+            ListState::default().with_selected(selected.map(|selected| selected - offset));
+        StatefulWidget::render(list, list_area, buf, &mut list_state);
+
+        *grep.state.offset_mut() = offset;
+
+        let Some((path, line_number)) = grep
+            .selected_match()
+            .map(|(path, found)| (path, found.line_number))
+        else {
+            Block::bordered()
+                .border_set(border::ROUNDED)
+                .render(preview_area, buf);
+            return;
+        };
+
+        let block = Block::bordered()
+            .title(Line::from(vec![
+                " ".into(),
+                path.display().to_string().yellow(),
+                format!(":{} ", line_number).into(),
+            ]))
+            .border_set(border::ROUNDED);
+
+        // the file is only read again once another match is selected
+        let height = block.inner(preview_area).height as usize;
+        let preview = match grep.preview.take() {
+            Some(preview)
+                if preview.path == path
+                    && preview.line_number == line_number
+                    && preview.height == height =>
+            {
+                preview
+            }
+            _ => GrepPreview {
+                lines: preview_lines(&path, &grep.pattern, line_number, height),
+                path,
+                line_number,
+                height,
+            },
+        };
+
+        Paragraph::new(preview.lines.clone())
+            .block(block)
+            .render(preview_area, buf);
+        grep.preview = Some(preview);
+    }
+}
+
+// Runs on its own thread and ends once every file was read, too many lines
+// matched or the results were closed
+fn search_files(root: &Path, pattern: &Regex, show_hidden: bool, sender: Sender<GrepFile>) {
+    let found = AtomicUsize::new(0);
+
+    WalkBuilder::new(root)
+        .hidden(!show_hidden)
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .build_parallel()
+        .run(|| {
+            let sender = sender.clone();
+            let found = &found;
+
+            Box::new(move |entry| {
+                let Ok(entry) = entry else {
+                    return WalkState::Continue;
+                };
+
+                if !entry
+                    .file_type()
+                    .is_some_and(|file_type| file_type.is_file())
+                {
+                    return WalkState::Continue;
+                }
+
+                let matches = search_file(entry.path(), pattern);
+                if matches.is_empty() {
+                    return WalkState::Continue;
+                }
+
+                if found.fetch_add(matches.len(), Ordering::Relaxed) >= MAX_MATCHES {
+                    return WalkState::Quit;
+                }
+
+                let path = entry
+                    .path()
+                    .strip_prefix(root)
+                    .unwrap_or(entry.path())
+                    .to_path_buf();
+
+                match sender.send(GrepFile { path, matches }) {
+                    Ok(()) => WalkState::Continue,
+                    Err(_) => WalkState::Quit,
+                }
+            })
+        });
+}
+
+// Reads the file a line at a time, the lines before a match are kept until
+// it's known whether one comes and the ones after it are owed to it
+fn search_file(path: &Path, pattern: &Regex) -> Vec<GrepMatch> {
+    let Ok(file) = File::open(path) else {
+        return Vec::new();
+    };
+    let mut reader = BufReader::with_capacity(BINARY_CHECK_SIZE, file);
+
+    match reader.fill_buf() {
+        Ok(start) if !start.contains(&0) => {}
+        _ => return Vec::new(),
+    }
+
+    let mut matches: Vec<GrepMatch> = Vec::new();
+    let mut before = VecDeque::with_capacity(CONTEXT_LINES + 1);
+    let mut after = 0;
+    let mut buffer = Vec::new();
+    let mut line_number = 0;
+
+    while matches.len() < MAX_MATCHES && read_line(&mut reader, &mut buffer).unwrap_or(false) {
+        line_number += 1;
+        let line = String::from_utf8_lossy(&buffer);
+
+        if pattern.is_match(&line) {
+            matches.push(GrepMatch {
+                line_number,
+                line: truncate_line(&line),
+                context: before.drain(..).collect(),
+            });
+            after = CONTEXT_LINES;
+        } else if after > 0
+            && let Some(found) = matches.last_mut()
+        {
+            // only listed after the previous match, never again before the next
+            found.context.push((line_number, truncate_line(&line)));
+            after -= 1;
+        } else {
+            before.push_back((line_number, truncate_line(&line)));
+            if before.len() > CONTEXT_LINES {
+                before.pop_front();
+            }
+        }
+    }
+
+    matches
+}
+
+// Reads the next line into `buffer` without its line ending, longer lines than
+// MAX_SEARCHED_LINE only have their start kept. False at the end of the file
+fn read_line(reader: &mut impl BufRead, buffer: &mut Vec<u8>) -> io::Result<bool> {
+    buffer.clear();
+
+    let read = reader
+        .by_ref()
+        .take(MAX_SEARCHED_LINE)
+        .read_until(b'\n', buffer)?;
+    if read == 0 {
+        return Ok(false);
+    }
+
+    if buffer.last() == Some(&b'\n') {
+        buffer.pop();
+        if buffer.last() == Some(&b'\r') {
+            buffer.pop();
+        }
+    } else if read as u64 == MAX_SEARCHED_LINE {
+        reader.skip_until(b'\n')?;
+    }
+
+    Ok(true)
+}
+
+fn truncate_line(line: &str) -> String {
+    line.chars().take(MAX_LINE_LENGTH).collect()
+}
+
+// The lines around `line_number` with it in the middle of the pane
+fn preview_lines(
+    path: &Path,
+    pattern: &Regex,
+    line_number: usize,
+    height: usize,
+) -> Vec<Line<'static>> {
+    let Ok(file) = File::open(path) else {
+        return vec![Line::from("Failed to read file")];
+    };
+    let mut reader = BufReader::new(file);
+
+    let first = line_number.saturating_sub(height / 2).max(1);
+    let mut lines = Vec::new();
+    let mut buffer = Vec::new();
+    let mut number = 0;
+
+    // lines before the window are read too, but never kept
+    while lines.len() < height && read_line(&mut reader, &mut buffer).unwrap_or(false) {
+        number += 1;
+        if number < first {
+            continue;
+        }
+
+        let line = truncate_line(&String::from_utf8_lossy(&buffer));
+        let number_text = format!("{:>5} ", number);
+
+        if number == line_number {
+            let mut spans = vec![number_text.yellow().bold()];
+            spans.extend(highlight_pattern(pattern, &line).spans);
+            lines.push(Line::from(spans));
+        } else {
+            lines.push(Line::from(vec![number_text.dark_gray(), line.into()]));
+        }
+    }
+
+    lines
+}
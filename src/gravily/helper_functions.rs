@@ -1,16 +1,123 @@
 use super::FileManager;
+use crate::gravily::ArchiveKind;
+use crate::gravily::{ListingCache, SortEntry};
 
+use ratatui::DefaultTerminal;
+
+use std::env::var;
 use std::fs::File;
 use std::fs::metadata;
+use std::fs::read_dir;
+use std::fs::remove_dir_all;
 use std::fs::remove_file;
 use std::fs::rename;
+use std::fs::write;
+use std::io;
 
-use std::path::Path;
-
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 
 impl FileManager {
-    pub fn get_hovered_dir(&mut self) -> PathBuf {
+    pub fn read_path_items(&mut self) {
+        if self.is_in_archive() {
+            self.read_archive_items();
+            return;
+        }
+
+        // results are kept when a directory is entered from them, and listed
+        // again once their root is back, until they're closed
+        if self.is_showing_find_results() {
+            self.read_find_items();
+            return;
+        }
+
+        match self.read_dir_entries() {
+            Ok(mut entries) => {
+                self.refresh_ignore_rules();
+
+                entries.retain(|entry| self.is_shown(&entry.name, entry.is_dir));
+
+                self.sort.sort(&mut entries);
+                self.ignored_items = entries
+                    .iter()
+                    .filter(|entry| self.is_ignored(&entry.name, entry.is_dir))
+                    .map(|entry| entry.name.clone())
+                    .collect();
+                self.path_items = entries.into_iter().map(|entry| entry.name).collect();
+            }
+
+            Err(e) => {
+                self.error = format!("Error entering dir: {:#?}: {}", &self.path, e);
+
+                // it was readable when it was opened, so it went away or lost
+                // its permissions since. The history is left as it is
+                if let Some(ancestor) = self
+                    .path
+                    .ancestors()
+                    .skip(1)
+                    .find(|ancestor| read_dir(ancestor).is_ok())
+                {
+                    self.open_location(ancestor.to_path_buf());
+                }
+            }
+        }
+    }
+
+    // Every entry of the current directory, only read again once the
+    // directory itself changed since the listing is redrawn on every event.
+    // Sizes and times of the entries can change without the directory
+    // changing, so listings sorted by them are always read again
+    fn read_dir_entries(&mut self) -> io::Result<Vec<SortEntry>> {
+        let modified = metadata(&self.path)?.modified()?;
+
+        if let Some(cache) = &self.listing_cache
+            && !self.sort.mode.reads_metadata()
+            && cache.path == self.path
+            && cache.modified == modified
+            && cache.mode == self.sort.mode
+        {
+            return Ok(cache.entries.clone());
+        }
+
+        let entries: Vec<SortEntry> = read_dir(&self.path)?
+            .filter_map(|entry_result| entry_result.ok())
+            .map(|entry| SortEntry::from_dir_entry(&entry, self.sort.mode))
+            .collect();
+
+        if self.sort.mode.reads_metadata() {
+            return Ok(entries);
+        }
+
+        self.listing_cache = Some(ListingCache {
+            path: self.path.clone(),
+            modified,
+            mode: self.sort.mode,
+            entries: entries.clone(),
+        });
+
+        Ok(entries)
+    }
+
+    // Keeps the cursor on the same entry when a change reorders or filters
+    // the listing
+    pub fn reload_keeping_hovered(&mut self, change: impl FnOnce(&mut Self)) {
+        let hovered = self
+            .state
+            .selected()
+            .and_then(|selected| self.path_items.get(selected))
+            .cloned();
+
+        change(self);
+        self.read_path_items();
+
+        if let Some(index) =
+            hovered.and_then(|hovered| self.path_items.iter().position(|item| *item == hovered))
+        {
+            self.state.select(Some(index));
+        }
+    }
+
+    pub fn get_hovered_dir(&self) -> PathBuf {
         if let Some(path_val) = self.state.selected() {
             let cur_path: PathBuf = [&self.path, &PathBuf::from(&self.path_items[path_val])]
                 .iter()
@@ -22,8 +129,40 @@ impl FileManager {
         PathBuf::from(&self.path)
     }
 
+    // Marked entries, or the hovered one when nothing is marked
+    pub fn selected_paths(&self) -> Vec<PathBuf> {
+        if !self.marked.is_empty() {
+            return self.marked.iter().cloned().collect();
+        }
+
+        let hovered = self.get_hovered_dir();
+        if hovered == self.path {
+            return Vec::new();
+        }
+
+        vec![hovered]
+    }
+
+    pub fn selection_description(&self) -> String {
+        match self.selected_paths().as_slice() {
+            [path] => format!("{:?}", path.file_name().unwrap_or(path.as_os_str())),
+            paths => format!("{} marked items", paths.len()),
+        }
+    }
+
+    pub fn toggle_mark(&mut self) {
+        let hovered = self.get_hovered_dir();
+        if hovered == self.path {
+            return;
+        }
+
+        if !self.marked.remove(&hovered) {
+            self.marked.insert(hovered);
+        }
+    }
+
     pub fn is_hovering(&self) -> bool {
-        if let Some(_) = self.state.selected() {
+        if self.state.selected().is_some() {
             return true;
         }
 
@@ -32,16 +171,19 @@ impl FileManager {
 
     pub fn enter_hovered_dir(&mut self) {
         if let Some(path_val) = self.state.selected() {
-            let new_path: PathBuf = [&self.path, &PathBuf::from(&self.path_items[path_val])]
-                .iter()
-                .collect();
+            let new_path = self.path.join(&self.path_items[path_val]);
+
+            if self.is_in_archive() {
+                if self.is_archive_dir(&new_path) {
+                    self.go_to(new_path);
+                }
+                return;
+            }
 
             match metadata(&new_path) {
                 Ok(metadata) => {
-                    if metadata.is_dir() {
-                        self.path.push(&PathBuf::from(&self.path_items[path_val]));
-                        self.past_states.push(path_val);
-                        self.state.select_first();
+                    if metadata.is_dir() || ArchiveKind::from_path(&new_path).is_some() {
+                        self.go_to(new_path);
                     }
                 }
                 Err(e) => {
@@ -55,26 +197,44 @@ impl FileManager {
         }
     }
 
-    pub fn load_as_ansi(&mut self, path: &PathBuf) {
-        Path::new(path)
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("<unknown>")
-            .to_string();
+    pub fn open_dir(&mut self, path: &Path) {
+        self.go_to(path.to_path_buf());
     }
 
-    pub fn exit_dir(&mut self) {
-        self.path.pop();
+    // Opens the directory holding `path` with the cursor on it
+    pub fn reveal_path(&mut self, path: &Path) {
+        let (Some(parent), Some(file_name)) = (path.parent(), path.file_name()) else {
+            return;
+        };
 
-        let past_state = self.past_states.pop();
+        self.go_to(parent.to_path_buf());
+        if self.path != parent {
+            return;
+        }
 
-        if past_state != None {
-            self.state.select(past_state);
-        } else {
-            self.state.select_first();
+        match self.path_items.iter().position(|item| item == file_name) {
+            Some(index) => self.state.select(Some(index)),
+            None => self.error = format!("{:?} isn't shown in the listing", file_name),
         }
     }
 
+    // Goes up with the cursor on the directory that was just left
+    pub fn exit_dir(&mut self) {
+        if self.is_showing_find_results() {
+            self.close_find_results();
+            return;
+        }
+
+        let (Some(parent), Some(file_name)) = (self.path.parent(), self.path.file_name()) else {
+            return;
+        };
+
+        let parent = parent.to_path_buf();
+        self.last_selected
+            .insert(parent.clone(), file_name.to_os_string());
+        self.go_to(parent);
+    }
+
     pub fn create_file(&mut self) {
         self.error = String::new();
 
@@ -84,8 +244,8 @@ impl FileManager {
         let new_file = File::create_new(new_file_path);
 
         match new_file {
-            Ok(_new_file) => return,
-            Err(e) => self.error = format!("Error making {}: {}", file_name, e.to_string()),
+            Ok(_new_file) => (),
+            Err(e) => self.error = format!("Error making {}: {}", file_name, e),
         }
     }
 
@@ -103,37 +263,226 @@ impl FileManager {
             return;
         }
 
-        let new_file: PathBuf = [&self.path, &PathBuf::from(file_name)].iter().collect();
+        // find results can be further down than the current directory
+        let new_file = hovered_file.with_file_name(file_name);
         let renamed_file = rename(&hovered_file, new_file);
 
         match renamed_file {
-            Ok(_renamed_file) => return,
-            Err(e) => {
-                self.error = format!("Error renaming file {:?}: {}", hovered_file, e.to_string())
-            }
+            Ok(_renamed_file) => (),
+            Err(e) => self.error = format!("Error renaming file {:?}: {}", hovered_file, e),
         }
     }
 
-    pub fn remove_file(&mut self) {
+    // Removes the marked (or hovered) items, directories with everything in them
+    pub fn remove_selection(&mut self) {
         self.error = String::new();
 
-        let file_dir = self.get_hovered_dir();
+        let mut errors = Vec::new();
 
-        let removed_file = remove_file(&file_dir);
+        for path in self.selected_paths() {
+            let removed = match path.symlink_metadata() {
+                Ok(metadata) if metadata.is_dir() => remove_dir_all(&path),
+                Ok(_) => remove_file(&path),
+                Err(e) => Err(e),
+            };
 
-        match removed_file {
-            Ok(_removed_file) => return,
-            Err(e) => {
-                self.error = format!(
-                    "Error deleting {}: {}",
-                    file_dir.to_str().unwrap(),
-                    e.to_string()
-                )
+            if let Err(e) = removed {
+                errors.push(format!("{}: {}", path.display(), e));
             }
         }
+
+        self.marked.clear();
+        self.prune_find_results();
+
+        if !errors.is_empty() {
+            self.error = format!("Error deleting {}", errors.join("; "));
+        }
+    }
+
+    // Hands the terminal over to $VISUAL or $EDITOR (vi when neither is set)
+    // with the cursor on `line_number`, until it exits
+    pub fn open_in_editor(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        path: &Path,
+        line_number: usize,
+    ) {
+        let editor = var("VISUAL")
+            .or_else(|_| var("EDITOR"))
+            .unwrap_or_else(|_| String::from("vi"));
+
+        // editors like "code --wait" come with their own arguments
+        let mut words = editor.split_whitespace();
+        let Some(program) = words.next() else {
+            return;
+        };
+
+        ratatui::restore();
+        let status = Command::new(program)
+            .args(words)
+            .arg(format!("+{}", line_number))
+            .arg(path)
+            .status();
+        *terminal = ratatui::init();
+
+        match status {
+            Ok(status) if status.success() => {}
+            Ok(status) => self.error = format!("{} exited with {}", program, status),
+            Err(e) => self.error = format!("Couldn't run {}: {}", program, e),
+        }
     }
 
     pub fn exit(&mut self) {
         self.exit = true;
     }
+
+    pub fn exit_and_cd(&mut self) {
+        self.cd_on_exit = true;
+        self.exit = true;
+    }
+
+    // Archives aren't directories a shell can enter, their folder is
+    // written instead
+    pub fn write_choosedir(&self) -> io::Result<()> {
+        let Some(file) = &self.options.choosedir else {
+            return Ok(());
+        };
+
+        let dir = match &self.archive {
+            Some(archive) => archive.archive_path.parent().unwrap_or(&self.path),
+            None => &self.path,
+        };
+
+        let mut contents = path_bytes(dir);
+        contents.push(b'\n');
+
+        write(file, contents)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", file.display(), e)))
+    }
+}
+
+// Human readable size, "512 B", "1.5 KiB", "20.0 MiB"...
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+// First row to draw so the selected one is on screen, scrolling as little as
+// possible from where the list was. Lists only build the rows from here on
+pub fn scroll_offset(offset: usize, selected: Option<usize>, visible_rows: usize) -> usize {
+    match selected {
+        Some(selected) if selected < offset => selected,
+        Some(selected) if visible_rows > 0 && selected >= offset + visible_rows => {
+            selected + 1 - visible_rows
+        }
+        _ => offset,
+    }
+}
+
+// The path as it is on disk, so shells can cd to names that aren't UTF-8
+#[cfg(unix)]
+pub fn path_bytes(path: &Path) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+
+    path.as_os_str().as_bytes().to_vec()
+}
+
+#[cfg(not(unix))]
+pub fn path_bytes(path: &Path) -> Vec<u8> {
+    path.display().to_string().into_bytes()
+}
+
+#[cfg(unix)]
+pub fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    use std::os::unix::ffi::OsStringExt;
+
+    PathBuf::from(std::ffi::OsString::from_vec(bytes))
+}
+
+#[cfg(not(unix))]
+pub fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+// A field of the tab separated data files. Backslashes, tabs and line breaks are
+// escaped and bytes that aren't UTF-8 are written as \xHH
+pub fn escape_field(bytes: &[u8]) -> String {
+    let mut escaped = String::with_capacity(bytes.len());
+    for chunk in bytes.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            match c {
+                '\\' => escaped.push_str("\\\\"),
+                '\t' => escaped.push_str("\\t"),
+                '\n' => escaped.push_str("\\n"),
+                '\r' => escaped.push_str("\\r"),
+                _ => escaped.push(c),
+            }
+        }
+        for byte in chunk.invalid() {
+            escaped.push_str(&format!("\\x{:02x}", byte));
+        }
+    }
+    escaped
+}
+
+// Unknown escapes are kept as they are, so lines written before escaping still load
+pub fn unescape_field(field: &str) -> Vec<u8> {
+    let hex = |byte: u8| (byte as char).to_digit(16);
+    let mut bytes = Vec::with_capacity(field.len());
+    let mut rest = field.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        rest = tail;
+        if byte != b'\\' {
+            bytes.push(byte);
+            continue;
+        }
+        let (unescaped, tail) = match rest {
+            [b'\\', tail @ ..] => (b'\\', tail),
+            [b't', tail @ ..] => (b'\t', tail),
+            [b'n', tail @ ..] => (b'\n', tail),
+            [b'r', tail @ ..] => (b'\r', tail),
+            [b'x', high, low, tail @ ..]
+                if let (Some(high), Some(low)) = (hex(*high), hex(*low)) =>
+            {
+                ((high * 16 + low) as u8, tail)
+            }
+            _ => (b'\\', rest),
+        };
+        bytes.push(unescaped);
+        rest = tail;
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escaped_fields_round_trip() {
+        let field = b"tab\there\nnew\\line\r\xff\xfe end";
+        let escaped = escape_field(field);
+        assert_eq!(escaped, "tab\\there\\nnew\\\\line\\r\\xff\\xfe end");
+        assert!(!escaped.contains(['\t', '\n', '\r']));
+        assert_eq!(unescape_field(&escaped), field);
+    }
+
+    #[test]
+    fn unknown_escapes_are_kept() {
+        assert_eq!(unescape_field("C:\\dir\\x4"), b"C:\\dir\\x4");
+        assert_eq!(unescape_field("\\q\\"), b"\\q\\");
+    }
 }
@@ -0,0 +1,96 @@
+use super::FileManager;
+
+use std::fs::read_dir;
+use std::path::PathBuf;
+
+// Oldest places are forgotten past this many
+const MAX_HISTORY: usize = 100;
+
+impl FileManager {
+    // Every change of directory goes through here, what was ahead in the
+    // history is dropped like in a browser
+    pub fn go_to(&mut self, path: PathBuf) {
+        let previous = self.path.clone();
+
+        if self.open_location(path) && self.path != previous {
+            self.back_history.push(previous);
+            if self.back_history.len() > MAX_HISTORY {
+                self.back_history.remove(0);
+            }
+            self.forward_history.clear();
+        }
+    }
+
+    pub fn go_back(&mut self) {
+        let Some(path) = self.back_history.pop() else {
+            return;
+        };
+
+        let current = self.path.clone();
+        if self.open_location(path) {
+            self.forward_history.push(current);
+        }
+    }
+
+    pub fn go_forward(&mut self) {
+        let Some(path) = self.forward_history.pop() else {
+            return;
+        };
+
+        let current = self.path.clone();
+        if self.open_location(path) {
+            self.back_history.push(current);
+        }
+    }
+
+    // Opens a directory, an archive or a directory inside one, with the
+    // cursor back on the entry it was on the last time. Stays where it is
+    // when the location can't be read
+    pub fn open_location(&mut self, path: PathBuf) -> bool {
+        self.remember_hovered();
+
+        let opened = if path.is_dir() {
+            match read_dir(&path) {
+                Ok(_) => {
+                    self.archive = None;
+                    self.path = path;
+                    true
+                }
+                Err(e) => {
+                    self.error = format!("Error entering dir: {:#?}: {}", path, e);
+                    false
+                }
+            }
+        } else {
+            self.open_archive_location(path)
+        };
+
+        if !opened {
+            return false;
+        }
+
+        if !self.is_in_archive() {
+            self.record_visit(&self.path.clone());
+        }
+        self.read_path_items();
+
+        let index = self
+            .last_selected
+            .get(&self.path)
+            .and_then(|name| self.path_items.iter().position(|item| item == name))
+            .unwrap_or(0);
+        self.state.select(Some(index));
+
+        true
+    }
+
+    fn remember_hovered(&mut self) {
+        if let Some(name) = self
+            .state
+            .selected()
+            .and_then(|selected| self.path_items.get(selected))
+        {
+            self.last_selected.insert(self.path.clone(), name.clone());
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use super::FileManager;
+use crate::gravily::{IgnoreMode, IgnoreRules};
+
+use ignore::gitignore::GitignoreBuilder;
+
+use std::ffi::OsStr;
+use std::path::Path;
+
+impl FileManager {
+    pub fn toggle_hidden(&mut self) {
+        self.reload_keeping_hovered(|file_manager| file_manager.show_hidden ^= true);
+    }
+
+    pub fn cycle_ignore_mode(&mut self) {
+        let mode = match self.ignore_mode {
+            IgnoreMode::Off => IgnoreMode::Dim,
+            IgnoreMode::Dim => IgnoreMode::Hide,
+            IgnoreMode::Hide => IgnoreMode::Off,
+        };
+
+        self.reload_keeping_hovered(|file_manager| file_manager.ignore_mode = mode);
+    }
+
+    pub fn visibility_description(&self) -> String {
+        let hidden = if self.show_hidden {
+            "dotfiles shown"
+        } else {
+            "dotfiles hidden"
+        };
+
+        match self.ignore_mode {
+            IgnoreMode::Off => hidden.to_string(),
+            IgnoreMode::Dim => format!("{}, ignored dimmed", hidden),
+            IgnoreMode::Hide => format!("{}, ignored hidden", hidden),
+        }
+    }
+
+    pub fn refresh_ignore_rules(&mut self) {
+        if self.ignore_mode == IgnoreMode::Off {
+            self.ignore_rules = None;
+            return;
+        }
+
+        if self
+            .ignore_rules
+            .as_ref()
+            .is_some_and(|rules| rules.dir == self.path)
+        {
+            return;
+        }
+
+        self.ignore_rules = Some(IgnoreRules::load(&self.path));
+    }
+
+    // Rules for a directory other than the current one, like the previewed one
+    pub fn ignore_rules_for(&self, dir: &Path) -> Option<IgnoreRules> {
+        (self.ignore_mode != IgnoreMode::Off).then(|| IgnoreRules::load(dir))
+    }
+
+    pub fn is_ignored(&self, file_name: &OsStr, is_dir: bool) -> bool {
+        self.current_ignore_rules()
+            .is_some_and(|rules| rules.is_ignored(file_name, is_dir))
+    }
+
+    pub fn is_shown(&self, file_name: &OsStr, is_dir: bool) -> bool {
+        self.is_shown_in(&self.path, self.current_ignore_rules(), file_name, is_dir)
+    }
+
+    // Whether an entry of `dir` would be listed once `dir` was opened, with
+    // its own filter and ignore rules
+    pub fn is_shown_in(
+        &self,
+        dir: &Path,
+        rules: Option<&IgnoreRules>,
+        file_name: &OsStr,
+        is_dir: bool,
+    ) -> bool {
+        if !self.show_hidden && is_hidden(file_name) {
+            return false;
+        }
+
+        if self
+            .filters
+            .get(dir)
+            .is_some_and(|filter| !filter.is_match(file_name))
+        {
+            return false;
+        }
+
+        self.ignore_mode != IgnoreMode::Hide
+            || !rules.is_some_and(|rules| rules.is_ignored(file_name, is_dir))
+    }
+
+    fn current_ignore_rules(&self) -> Option<&IgnoreRules> {
+        self.ignore_rules
+            .as_ref()
+            .filter(|rules| rules.dir == self.path)
+    }
+}
+
+impl IgnoreRules {
+    // Loads the .gitignore and .ignore files of `dir` and its parents, up to
+    // the root of the repository it's in
+    fn load(dir: &Path) -> Self {
+        let mut matchers = Vec::new();
+
+        for ancestor in dir.ancestors() {
+            let mut builder = GitignoreBuilder::new(ancestor);
+            for file_name in [".gitignore", ".ignore"] {
+                let path = ancestor.join(file_name);
+                if path.is_file() {
+                    // broken lines are skipped, the rest of the file still applies
+                    let _ = builder.add(path);
+                }
+            }
+
+            if let Ok(matcher) = builder.build()
+                && !matcher.is_empty()
+            {
+                matchers.push(matcher);
+            }
+
+            if ancestor.join(".git").exists() {
+                break;
+            }
+        }
+
+        Self {
+            dir: dir.to_path_buf(),
+            matchers,
+        }
+    }
+
+    // The closest ignore file with a matching rule decides
+    pub fn is_ignored(&self, file_name: &OsStr, is_dir: bool) -> bool {
+        let path = self.dir.join(file_name);
+
+        self.matchers
+            .iter()
+            .map(|matcher| matcher.matched_path_or_any_parents(&path, is_dir))
+            .find(|matched| !matched.is_none())
+            .is_some_and(|matched| matched.is_ignore())
+    }
+}
+
+pub fn is_hidden(file_name: &OsStr) -> bool {
+    file_name.as_encoded_bytes().starts_with(b".")
+}
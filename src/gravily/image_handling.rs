@@ -0,0 +1,567 @@
+use super::FileManager;
+use crate::gravily::{Animation, CachedImage, ImageInfo, ImageKey, ImageLoader, ImageWidget};
+
+use exif::{In, Tag, Value};
+use image::{
+    AnimationDecoder, ColorType, DynamicImage, Frame, GenericImageView, ImageDecoder, ImageFormat,
+    ImageReader, ImageResult, Pixel,
+    codecs::{gif::GifDecoder, png::PngDecoder, webp::WebPDecoder},
+    imageops::FilterType,
+};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Alignment, Constraint, Layout, Rect},
+    style::{Color, Style, Stylize},
+    text::Line,
+    widgets::{Block, Borders, Paragraph, StatefulWidget, Widget},
+};
+use ratatui_image::{
+    Resize, ResizeEncodeRender, StatefulImage,
+    picker::{Picker, ProtocolType},
+};
+
+use std::collections::VecDeque;
+use std::fs::{File, metadata};
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::mpsc::{Receiver, Sender, channel};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+const IMAGE_CACHE_SIZE: usize = 128;
+const MAX_ANIMATION_FRAMES: usize = 200;
+// Animations stop at this many bytes of shrunk frames, whichever limit comes first
+const MAX_ANIMATION_BYTES: usize = 64 * 1024 * 1024;
+// Browsers treat very short frame delays as "unspecified" and fall back to this
+const DEFAULT_FRAME_DELAY: Duration = Duration::from_millis(100);
+const IMAGE_RESIZE: Resize = Resize::Fit(Some(FilterType::Triangle));
+
+// Asks the terminal for kitty, sixel or iTerm2 support. Has to run after the
+// alternate screen is entered and before any events are read.
+pub fn detect_graphics_protocol() -> Option<Picker> {
+    match Picker::from_query_stdio() {
+        Ok(picker) if picker.protocol_type() != ProtocolType::Halfblocks => Some(picker),
+        _ => None,
+    }
+}
+
+fn modified_time(path: &Path) -> SystemTime {
+    metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .unwrap_or(SystemTime::UNIX_EPOCH)
+}
+
+impl FileManager {
+    // Draws the image with its metadata underneath, returns false if the file
+    // isn't a readable image
+    pub fn render_image_preview(&mut self, path: &Path, area: Rect, buf: &mut Buffer) -> bool {
+        if ImageFormat::from_path(path).is_err() {
+            return false;
+        }
+
+        let modified = modified_time(path);
+        let is_current = self
+            .image_info
+            .as_ref()
+            .is_some_and(|info| info.path == path && info.modified == modified);
+
+        if !is_current {
+            self.image_info = Some(ImageInfo {
+                path: path.to_path_buf(),
+                modified,
+                fields: read_image_info(path),
+            });
+        }
+
+        let lines: Vec<Line> = self
+            .image_info
+            .iter()
+            .flat_map(|info| info.fields.iter())
+            .map(|(name, value)| {
+                Line::from(vec![format!("{}: ", name).blue(), value.clone().into()])
+            })
+            .collect();
+
+        let info_height = (lines.len() as u16 + 1).min(area.height / 2);
+        let [image_area, info_area] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(info_height)]).areas(area);
+
+        if !self.render_image(path, image_area, buf) {
+            return false;
+        }
+
+        Paragraph::new(lines)
+            .block(Block::new().borders(Borders::TOP).title(" Info "))
+            .render(info_area, buf);
+
+        true
+    }
+
+    pub fn render_image(&mut self, path: &Path, area: Rect, buf: &mut Buffer) -> bool {
+        if ImageFormat::from_path(path).is_err() {
+            return false;
+        }
+
+        let Some(image_loader) = &mut self.image_loader else {
+            return false;
+        };
+
+        let key = ImageKey {
+            path: path.to_path_buf(),
+            modified: modified_time(path),
+            size: (area.width, area.height),
+        };
+
+        match image_loader.get(&key) {
+            Some(Some(image)) => {
+                render_cached_image(image, area, buf);
+                image_loader.shown.push(key);
+            }
+            Some(None) => return false,
+            None => {
+                image_loader.request(key);
+
+                let placeholder = area.rows().nth(area.height as usize / 2).unwrap_or(area);
+                Paragraph::new("Loading image...".dark_gray())
+                    .alignment(Alignment::Center)
+                    .render(placeholder, buf);
+            }
+        }
+
+        true
+    }
+}
+
+fn render_cached_image(image: &mut CachedImage, area: Rect, buf: &mut Buffer) {
+    match image {
+        CachedImage::HalfBlocks(img) => ImageWidget::new(img).render(area, buf),
+        CachedImage::Protocol(protocol) => {
+            StatefulImage::default()
+                .resize(IMAGE_RESIZE)
+                .render(area, buf, protocol);
+        }
+        CachedImage::Animation(animation) => {
+            render_cached_image(animation.current_frame(), area, buf)
+        }
+    }
+}
+
+impl Animation {
+    fn current_frame(&mut self) -> &mut CachedImage {
+        let now = Instant::now();
+        let shown_at = *self.shown_at.get_or_insert(now);
+
+        if now.duration_since(shown_at) >= self.frames[self.current].1 {
+            self.current = (self.current + 1) % self.frames.len();
+            self.shown_at = Some(now);
+        }
+
+        &mut self.frames[self.current].0
+    }
+
+    fn next_frame(&self) -> Option<Instant> {
+        self.shown_at
+            .map(|shown_at| shown_at + self.frames[self.current].1)
+    }
+}
+
+impl ImageLoader {
+    pub fn new(picker: Option<Picker>) -> Self {
+        let (request_sender, requests) = channel::<Vec<ImageKey>>();
+        let (results, result_receiver) = channel();
+
+        thread::spawn(move || decode_images(requests, results, picker));
+
+        Self {
+            requests: request_sender,
+            results: result_receiver,
+            pending: Vec::new(),
+            wanted: Vec::new(),
+            shown: Vec::new(),
+            cache: VecDeque::new(),
+        }
+    }
+
+    pub fn get(&mut self, key: &ImageKey) -> Option<&mut Option<CachedImage>> {
+        let index = self.cache.iter().position(|(cached, _)| cached == key)?;
+
+        // move to the front so the least recently shown image is evicted first
+        let entry = self.cache.remove(index)?;
+        self.cache.push_front(entry);
+
+        self.cache.front_mut().map(|(_, image)| image)
+    }
+
+    pub fn request(&mut self, key: ImageKey) {
+        if !self.wanted.contains(&key) {
+            self.wanted.push(key);
+        }
+    }
+
+    // Sends the images requested this frame to the worker, pauses every
+    // animation that wasn't drawn and returns when the ones on screen want
+    // their next frame
+    pub fn finish_frame(&mut self) -> Option<Instant> {
+        let wanted = std::mem::take(&mut self.wanted);
+        if wanted != self.pending && self.requests.send(wanted.clone()).is_ok() {
+            self.pending = wanted;
+        }
+
+        let shown = std::mem::take(&mut self.shown);
+        let mut next_frame: Option<Instant> = None;
+
+        for (key, image) in self.cache.iter_mut() {
+            if let Some(CachedImage::Animation(animation)) = image {
+                if shown.contains(key) {
+                    next_frame = match (next_frame, animation.next_frame()) {
+                        (Some(current), Some(next)) => Some(current.min(next)),
+                        (current, next) => current.or(next),
+                    };
+                } else {
+                    animation.shown_at = None;
+                }
+            }
+        }
+
+        next_frame
+    }
+
+    pub fn is_loading(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    pub fn receive(&mut self) {
+        while let Ok((key, image)) = self.results.try_recv() {
+            self.pending.retain(|pending| pending != &key);
+
+            self.cache.retain(|(cached, _)| cached != &key);
+            self.cache.push_front((key, image));
+            self.cache.truncate(IMAGE_CACHE_SIZE);
+        }
+    }
+}
+
+fn decode_images(
+    requests: Receiver<Vec<ImageKey>>,
+    results: Sender<(ImageKey, Option<CachedImage>)>,
+    picker: Option<Picker>,
+) {
+    let mut queue = VecDeque::new();
+
+    loop {
+        if queue.is_empty() {
+            match requests.recv() {
+                Ok(keys) => queue = VecDeque::from(keys),
+                Err(_) => return,
+            }
+        }
+
+        // a newer request replaces whatever is left of the old one, so images
+        // scrolled past quickly are never decoded
+        while let Ok(keys) = requests.try_recv() {
+            queue = VecDeque::from(keys);
+        }
+
+        let Some(key) = queue.pop_front() else {
+            continue;
+        };
+
+        let image = decode_image(&key, picker.as_ref());
+
+        if results.send((key, image)).is_err() {
+            return;
+        }
+    }
+}
+
+fn decode_image(key: &ImageKey, picker: Option<&Picker>) -> Option<CachedImage> {
+    match open_animation(key, picker) {
+        Some(frames) => Some(CachedImage::Animation(Animation {
+            frames,
+            current: 0,
+            shown_at: None,
+        })),
+        None => open_oriented(&key.path)
+            .ok()
+            .and_then(|img| fit_image(img, key, picker)),
+    }
+}
+
+// Like image::open, but rotates and flips the image as its EXIF orientation asks
+pub fn open_oriented(path: &Path) -> ImageResult<DynamicImage> {
+    let mut decoder = ImageReader::open(path)?
+        .with_guessed_format()?
+        .into_decoder()?;
+
+    let orientation = decoder.orientation()?;
+    let mut img = DynamicImage::from_decoder(decoder)?;
+    img.apply_orientation(orientation);
+
+    Ok(img)
+}
+
+fn read_image_info(path: &Path) -> Vec<(&'static str, String)> {
+    let mut fields = Vec::new();
+
+    if let Ok(reader) = ImageReader::open(path).and_then(|reader| reader.with_guessed_format()) {
+        if let Some(format) = reader.format() {
+            fields.push(("Format", format!("{:?}", format).to_uppercase()));
+        }
+
+        if let Ok(decoder) = reader.into_decoder() {
+            let (width, height) = decoder.dimensions();
+            fields.push(("Dimensions", format!("{} x {} px", width, height)));
+            fields.push(("Colour", describe_color_type(decoder.color_type())));
+        }
+    }
+
+    let Ok(exif) = File::open(path)
+        .map(BufReader::new)
+        .map_err(exif::Error::from)
+        .and_then(|mut reader| exif::Reader::new().read_from_container(&mut reader))
+    else {
+        return fields;
+    };
+
+    let ascii = |tag| match exif.get_field(tag, In::PRIMARY).map(|field| &field.value) {
+        Some(Value::Ascii(values)) => values
+            .first()
+            .map(|value| String::from_utf8_lossy(value).trim().to_string())
+            .filter(|value| !value.is_empty()),
+        _ => None,
+    };
+    let rational = |tag| match exif.get_field(tag, In::PRIMARY).map(|field| &field.value) {
+        Some(Value::Rational(values)) => values.first().map(|value| value.to_f64()),
+        _ => None,
+    };
+
+    let camera = match (ascii(Tag::Make), ascii(Tag::Model)) {
+        (Some(make), Some(model)) if model.starts_with(&make) => Some(model),
+        (Some(make), Some(model)) => Some(format!("{} {}", make, model)),
+        (make, model) => make.or(model),
+    };
+    if let Some(camera) = camera {
+        fields.push(("Camera", camera));
+    }
+
+    if let Some(lens) = ascii(Tag::LensModel) {
+        fields.push(("Lens", lens));
+    }
+
+    let exposure: Vec<String> = [
+        exif.get_field(Tag::ExposureTime, In::PRIMARY)
+            .map(|field| format!("{} s", field.display_value())),
+        rational(Tag::FNumber).map(|f_number| format!("f/{}", f_number)),
+        exif.get_field(Tag::PhotographicSensitivity, In::PRIMARY)
+            .and_then(|field| field.value.get_uint(0))
+            .map(|iso| format!("ISO {}", iso)),
+        rational(Tag::FocalLength).map(|focal_length| format!("{} mm", focal_length)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    if !exposure.is_empty() {
+        fields.push(("Exposure", exposure.join(", ")));
+    }
+
+    let coordinate = |tag, reference_tag, negative| {
+        let Some(Value::Rational(values)) =
+            exif.get_field(tag, In::PRIMARY).map(|field| &field.value)
+        else {
+            return None;
+        };
+
+        let [degrees, minutes, seconds] = values.get(..3)? else {
+            return None;
+        };
+        let value = degrees.to_f64() + minutes.to_f64() / 60.0 + seconds.to_f64() / 3600.0;
+
+        match ascii(reference_tag) {
+            Some(reference) if reference == negative => Some(-value),
+            _ => Some(value),
+        }
+    };
+    if let (Some(latitude), Some(longitude)) = (
+        coordinate(Tag::GPSLatitude, Tag::GPSLatitudeRef, "S"),
+        coordinate(Tag::GPSLongitude, Tag::GPSLongitudeRef, "W"),
+    ) {
+        fields.push(("GPS", format!("{:.5}, {:.5}", latitude, longitude)));
+    }
+
+    let orientation = exif
+        .get_field(Tag::Orientation, In::PRIMARY)
+        .and_then(|field| field.value.get_uint(0))
+        .and_then(|orientation| match orientation {
+            1 => Some("Normal"),
+            2 => Some("Flipped horizontally"),
+            3 => Some("Rotated 180°"),
+            4 => Some("Flipped vertically"),
+            5 => Some("Rotated 90° CW and flipped horizontally"),
+            6 => Some("Rotated 90° CW"),
+            7 => Some("Rotated 270° CW and flipped horizontally"),
+            8 => Some("Rotated 270° CW"),
+            _ => None,
+        });
+    if let Some(orientation) = orientation {
+        fields.push(("Orientation", orientation.to_string()));
+    }
+
+    // EXIF dates look like "2024:01:31 18:00:00"
+    if let Some(date) = ascii(Tag::DateTimeOriginal).or_else(|| ascii(Tag::DateTime)) {
+        fields.push(("Taken", date.replacen(':', "-", 2)));
+    }
+
+    fields
+}
+
+fn describe_color_type(color_type: ColorType) -> String {
+    let channels = match color_type {
+        ColorType::L8 | ColorType::L16 => "Grayscale",
+        ColorType::La8 | ColorType::La16 => "Grayscale with alpha",
+        ColorType::Rgb8 | ColorType::Rgb16 | ColorType::Rgb32F => "RGB",
+        ColorType::Rgba8 | ColorType::Rgba16 | ColorType::Rgba32F => "RGBA",
+        _ => return format!("{:?}", color_type),
+    };
+
+    format!(
+        "{}, {} bits per channel",
+        channels,
+        color_type.bits_per_pixel() / color_type.channel_count() as u16
+    )
+}
+
+// Returns None for still images (including single frame GIFs) so they go
+// through the regular decoder. Each frame is shrunk as soon as it's decoded so
+// only one of them is ever held at full resolution.
+fn open_animation(key: &ImageKey, picker: Option<&Picker>) -> Option<Vec<(CachedImage, Duration)>> {
+    let reader = BufReader::new(File::open(&key.path).ok()?);
+
+    let frames = match ImageFormat::from_path(&key.path).ok()? {
+        ImageFormat::Gif => GifDecoder::new(reader).ok()?.into_frames(),
+        ImageFormat::Png => {
+            let decoder = PngDecoder::new(reader).ok()?;
+            if !decoder.is_apng().ok()? {
+                return None;
+            }
+            decoder.apng().ok()?.into_frames()
+        }
+        ImageFormat::WebP => {
+            let decoder = WebPDecoder::new(reader).ok()?;
+            if !decoder.has_animation() {
+                return None;
+            }
+            decoder.into_frames()
+        }
+        _ => return None,
+    };
+
+    let mut fitted = Vec::new();
+    let mut total_bytes = 0;
+
+    for frame in frames.take(MAX_ANIMATION_FRAMES) {
+        let frame: Frame = frame.ok()?;
+
+        let (numerator, denominator) = frame.delay().numer_denom_ms();
+        let delay = Duration::from_millis((numerator / denominator.max(1)) as u64);
+
+        let delay = if delay < Duration::from_millis(20) {
+            DEFAULT_FRAME_DELAY
+        } else {
+            delay
+        };
+
+        let img = shrink_image(DynamicImage::ImageRgba8(frame.into_buffer()), key, picker);
+        total_bytes += img.as_bytes().len();
+        if total_bytes > MAX_ANIMATION_BYTES && !fitted.is_empty() {
+            break;
+        }
+
+        fitted.push((encode_image(img, key, picker)?, delay));
+    }
+
+    if fitted.len() < 2 {
+        return None;
+    }
+
+    Some(fitted)
+}
+
+fn fit_image(img: DynamicImage, key: &ImageKey, picker: Option<&Picker>) -> Option<CachedImage> {
+    encode_image(shrink_image(img, key, picker), key, picker)
+}
+
+// Shrinks to the pixel size of the preview first so the cache doesn't hold
+// full resolution photos
+fn shrink_image(img: DynamicImage, key: &ImageKey, picker: Option<&Picker>) -> DynamicImage {
+    let (width, height) = key.size;
+
+    let Some(picker) = picker else {
+        return img.resize(width as u32, height as u32 * 2, FilterType::Triangle);
+    };
+
+    let (font_width, font_height) = picker.font_size();
+    let max_width = width as u32 * font_width as u32;
+    let max_height = height as u32 * font_height as u32;
+
+    if img.width() > max_width || img.height() > max_height {
+        img.resize(max_width, max_height, FilterType::Triangle)
+    } else {
+        img
+    }
+}
+
+fn encode_image(img: DynamicImage, key: &ImageKey, picker: Option<&Picker>) -> Option<CachedImage> {
+    let Some(picker) = picker else {
+        return Some(CachedImage::HalfBlocks(img));
+    };
+
+    let (width, height) = key.size;
+    let mut protocol = picker.new_resize_protocol(img);
+    protocol.resize_encode(&IMAGE_RESIZE, Rect::new(0, 0, width, height));
+
+    match protocol.last_encoding_result() {
+        Some(Err(_)) => None,
+        _ => Some(CachedImage::Protocol(protocol)),
+    }
+}
+
+impl Widget for ImageWidget<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        // Each cell holds two vertically stacked pixels, so fitting into
+        // (width, height * 2) keeps the aspect ratio of the source image
+        let (max_width, max_height) = (area.width as u32, area.height as u32 * 2);
+
+        let resized;
+        let img = if self.img.width() > max_width || self.img.height() > max_height {
+            resized = self.img.resize(max_width, max_height, FilterType::Triangle);
+            &resized
+        } else {
+            self.img
+        };
+
+        let (img_width, img_height) = img.dimensions();
+        let rows = img_height.div_ceil(2) as u16;
+
+        let x_offset = area.width.saturating_sub(img_width as u16) / 2;
+        let y_offset = area.height.saturating_sub(rows) / 2;
+
+        for y in 0..rows {
+            for x in 0..img_width as u16 {
+                let top_pixel = img.get_pixel(x as u32, y as u32 * 2).to_rgba();
+                let bottom_pixel = if y as u32 * 2 + 1 < img_height {
+                    img.get_pixel(x as u32, y as u32 * 2 + 1).to_rgba()
+                } else {
+                    top_pixel
+                };
+
+                let fg = Color::Rgb(top_pixel[0], top_pixel[1], top_pixel[2]);
+                let bg = Color::Rgb(bottom_pixel[0], bottom_pixel[1], bottom_pixel[2]);
+
+                if let Some(cell) = buf.cell_mut((area.x + x_offset + x, area.y + y_offset + y)) {
+                    cell.set_style(Style::default().fg(fg).bg(bg))
+                        .set_symbol("▀");
+                }
+            }
+        }
+    }
+}
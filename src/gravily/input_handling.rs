@@ -1,37 +1,185 @@
 use super::FileManager;
-use crate::gravily::InputMode;
-use crate::gravily::OperationType::{Add, Delete, Rename};
+use crate::gravily::OperationType::{
+    Add, Columns, Compress, Copy, Delete, Extract, ExtractHere, Move, Rename, Transform,
+};
+use crate::gravily::transform_handling::is_valid_transform;
+use crate::gravily::{ArchiveKind, ArchiveLoader, ImageLoader, InputMode};
 
 use crate::io;
-use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use tui_input::Input;
 use tui_input::backend::crossterm::EventHandler;
 
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+// Longest wait for input before redrawing while something runs in the
+// background, so its results show up without a key press
+const TICK_RATE: Duration = Duration::from_millis(50);
+
 enum Action {
     // Navigation Commands
     NextItem,
     PreviousItem,
     EnterItem,
     ExitItem,
+    GoBack,
+    GoForward,
+    GalleryLeft,
+    GalleryRight,
+    GalleryUp,
+    GalleryDown,
 
     // Operation Commands
     AddFile,
     DeleteFile,
+    CopySelection,
+    MoveSelection,
     RenameFile,
+    TransformImages,
+    CompressSelection,
+    ExtractArchive(bool),
+    ToggleMark,
+    ClearMarks,
+    EditColumns,
+    CycleSort,
+    ReverseSort,
+    ToggleSortCase,
+    ToggleDirectoriesFirst,
+    ToggleHidden,
+    CycleIgnoreMode,
+
+    // Search Commands
+    StartSearch(bool),
+    NextMatch,
+    PreviousMatch,
+    SearchInput,
+    ToggleSearchRegex,
+    FinishSearch,
+    CancelSearch,
+    ReadOnlyArchive,
+
+    // Filter Commands
+    StartFilter,
+    FilterInput,
+    FinishFilter,
+    ClearFilter,
+    MarkAll,
+
+    // Finder Commands
+    StartFinder,
+    FinderInput,
+    FinderNext,
+    FinderPrevious,
+    OpenFinderMatch,
+    CloseFinder,
+
+    // Content Search Commands
+    StartGrepInput,
+    StartGrep,
+    GrepNext,
+    GrepPrevious,
+    OpenGrepMatch(bool),
+    CloseGrep,
+
+    // Find Commands
+    StartFindInput,
+    StartFind,
+    CloseFindResults,
+
+    // Bookmark Commands
+    StartSetBookmark,
+    StartJumpToBookmark,
+    SetBookmark(char),
+    JumpToBookmark(char),
+    OpenBookmarkPicker,
+    BookmarkInput,
+    BookmarkNext,
+    BookmarkPrevious,
+    OpenPickedBookmark,
+    RemovePickedBookmark,
+    StartNamingPickedBookmark,
+    NameBookmark,
+    CancelNamingBookmark,
+    CloseBookmarkPicker,
+
+    // Jump Commands
+    StartJumpInput,
+    Jump,
+
+    // Go To Commands
+    StartGoTo,
+    GoToInput,
+    CompleteNext,
+    CompletePrevious,
+    GoTo,
+    CancelGoTo,
+
+    // Tab Commands
+    NewTab,
+    CloseTab,
+    SwitchTab(usize),
+    CycleTab(bool),
+    MoveTab(bool),
+    FillTabDestination(usize),
 
     // Input Mode Switching
     NavigationInputMode,
     CommandInputMode,
+    ToggleGallery,
 
     // Miscellaneous
     Enter,
     InputChar,
+    ConfirmChoice,
     CloseMessage,
     Quit,
+    QuitAndCd,
     None,
 }
 
 impl FileManager {
+    // Images still decoding or archive entries extracting, an animation
+    // playing, or a job or walk still sending results
+    fn is_busy(&self) -> bool {
+        self.image_loader
+            .as_ref()
+            .is_some_and(ImageLoader::is_loading)
+            || self
+                .archive_loader
+                .as_ref()
+                .is_some_and(ArchiveLoader::is_loading)
+            || self.next_frame.is_some()
+            || !self.jobs.is_empty()
+            || self
+                .finder
+                .as_ref()
+                .is_some_and(|finder| finder.walker.is_some())
+            || self
+                .grep
+                .as_ref()
+                .is_some_and(|grep| grep.searcher.is_some())
+            || self
+                .find_results
+                .as_ref()
+                .is_some_and(|find_results| find_results.walker.is_some())
+    }
+
     pub fn handle_events(&mut self) -> io::Result<()> {
+        // with nothing going on in the background the screen only changes
+        // after an event, so there's no point in waking up before one
+        if self.is_busy() {
+            let timeout = self.next_frame.map_or(TICK_RATE, |next_frame| {
+                next_frame
+                    .saturating_duration_since(Instant::now())
+                    .min(TICK_RATE)
+            });
+
+            if !event::poll(timeout)? {
+                return Ok(());
+            }
+        }
+
         let event = event::read()?;
         match &event {
             Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
@@ -41,11 +189,161 @@ impl FileManager {
                     Action::PreviousItem => self.state.select_previous(),
                     Action::EnterItem => self.enter_hovered_dir(),
                     Action::ExitItem => self.exit_dir(),
+                    Action::GoBack => self.go_back(),
+                    Action::GoForward => self.go_forward(),
+                    Action::GalleryLeft => self.move_gallery_selection(-1, 0),
+                    Action::GalleryRight => self.move_gallery_selection(1, 0),
+                    Action::GalleryUp => self.move_gallery_selection(0, -1),
+                    Action::GalleryDown => self.move_gallery_selection(0, 1),
 
                     // Operation Handling
                     Action::AddFile => self.input_mode = InputMode::Operation(Add),
                     Action::RenameFile => self.input_mode = InputMode::Operation(Rename),
                     Action::DeleteFile => self.input_mode = InputMode::Confirmation(Delete),
+                    Action::CopySelection => {
+                        self.input = Input::new(self.destination_input());
+                        self.input_mode = InputMode::Operation(Copy);
+                    }
+                    Action::MoveSelection => {
+                        self.input = Input::new(self.destination_input());
+                        self.input_mode = InputMode::Operation(Move);
+                    }
+                    Action::TransformImages => self.input_mode = InputMode::Operation(Transform),
+                    Action::CompressSelection => self.input_mode = InputMode::Operation(Compress),
+                    Action::ExtractArchive(into_folder) => {
+                        if ArchiveKind::from_path(&self.get_hovered_dir()).is_none() {
+                            self.error = String::from("Can only extract zip and tar archives");
+                        } else if into_folder {
+                            self.input_mode = InputMode::Confirmation(Extract);
+                        } else {
+                            self.input_mode = InputMode::Confirmation(ExtractHere);
+                        }
+                    }
+                    Action::ToggleMark => {
+                        self.toggle_mark();
+
+                        if self.is_gallery_open() {
+                            self.move_gallery_selection(1, 0);
+                        } else {
+                            self.state.select_next();
+                        }
+                    }
+                    Action::ClearMarks => self.marked.clear(),
+                    Action::CycleSort => self.cycle_sort_mode(),
+                    Action::ReverseSort => self.toggle_sort_reverse(),
+                    Action::ToggleSortCase => self.toggle_sort_case(),
+                    Action::ToggleDirectoriesFirst => self.toggle_directories_first(),
+                    Action::ToggleHidden => self.toggle_hidden(),
+                    Action::CycleIgnoreMode => self.cycle_ignore_mode(),
+
+                    // Search Handling
+                    Action::StartSearch(backward) => self.start_search(backward),
+                    Action::NextMatch => self.jump_to_match(false),
+                    Action::PreviousMatch => self.jump_to_match(true),
+                    Action::SearchInput => {
+                        self.input.handle_event(&event);
+                        self.update_search();
+                    }
+                    Action::ToggleSearchRegex => self.toggle_search_regex(),
+                    Action::FinishSearch => self.finish_search(),
+                    Action::CancelSearch => self.cancel_search(),
+
+                    // Filter Handling
+                    Action::StartFilter => self.start_filter(),
+                    Action::FilterInput => {
+                        self.input.handle_event(&event);
+                        self.update_filter();
+                    }
+                    Action::FinishFilter => self.finish_filter(),
+                    Action::ClearFilter => self.clear_filter(),
+                    Action::MarkAll => self.mark_all(),
+
+                    // Finder Handling
+                    Action::StartFinder => self.start_finder(),
+                    Action::FinderInput => {
+                        self.input.handle_event(&event);
+                        self.update_finder();
+                    }
+                    Action::FinderNext => self.move_finder_selection(true),
+                    Action::FinderPrevious => self.move_finder_selection(false),
+                    Action::OpenFinderMatch => self.open_finder_match(),
+                    Action::CloseFinder => self.close_finder(),
+
+                    // Content Search Handling
+                    Action::StartGrepInput => self.start_grep_input(),
+                    Action::StartGrep => self.start_grep(),
+                    Action::GrepNext => self.move_grep_selection(true),
+                    Action::GrepPrevious => self.move_grep_selection(false),
+                    Action::OpenGrepMatch(in_editor) => self.open_grep_match(in_editor),
+                    Action::CloseGrep => self.close_grep(),
+
+                    // Find Handling
+                    Action::StartFindInput => self.start_find_input(),
+                    Action::StartFind => {
+                        let query = self.input.value_and_reset();
+                        self.input_mode = InputMode::Navigation;
+                        self.start_find(&query);
+                    }
+                    Action::CloseFindResults => self.close_find_results(),
+
+                    // Bookmark Handling
+                    Action::StartSetBookmark => self.input_mode = InputMode::SetBookmark,
+                    Action::StartJumpToBookmark => self.input_mode = InputMode::JumpToBookmark,
+                    Action::SetBookmark(key) => self.set_bookmark(key),
+                    Action::JumpToBookmark(key) => self.jump_to_bookmark_key(key),
+                    Action::OpenBookmarkPicker => self.open_bookmark_picker(),
+                    Action::BookmarkInput => {
+                        self.input.handle_event(&event);
+                        self.update_bookmark_picker();
+                    }
+                    Action::BookmarkNext => self.move_bookmark_selection(true),
+                    Action::BookmarkPrevious => self.move_bookmark_selection(false),
+                    Action::OpenPickedBookmark => self.open_picked_bookmark(),
+                    Action::RemovePickedBookmark => self.remove_picked_bookmark(),
+                    Action::StartNamingPickedBookmark => self.start_naming_picked_bookmark(),
+                    Action::NameBookmark => {
+                        let name = self.input.value_and_reset();
+                        self.name_bookmark(&name);
+                    }
+                    Action::CancelNamingBookmark => self.finish_naming_bookmark(),
+                    Action::CloseBookmarkPicker => self.close_bookmark_picker(),
+
+                    // Jump Handling
+                    Action::StartJumpInput => self.start_jump_input(),
+                    Action::Jump => {
+                        let query = self.input.value_and_reset();
+                        self.jump(&query);
+                    }
+
+                    // Go To Handling
+                    Action::StartGoTo => self.start_goto(),
+                    Action::GoToInput => {
+                        self.input.handle_event(&event);
+                        self.reset_completion();
+                    }
+                    Action::CompleteNext => self.complete_goto(false),
+                    Action::CompletePrevious => self.complete_goto(true),
+                    Action::GoTo => {
+                        let input = self.input.value_and_reset();
+                        self.goto_path(&input);
+                    }
+                    Action::CancelGoTo => self.cancel_goto(),
+
+                    // Tab Handling
+                    Action::NewTab => self.new_tab(),
+                    Action::CloseTab => self.close_tab(),
+                    Action::SwitchTab(index) => self.switch_tab(index),
+                    Action::CycleTab(backward) => self.cycle_tab(backward),
+                    Action::MoveTab(backward) => self.move_tab(backward),
+                    Action::FillTabDestination(index) => self.fill_tab_destination(index),
+
+                    Action::EditColumns => {
+                        self.input = Input::new(self.columns_description());
+                        self.input_mode = InputMode::Operation(Columns);
+                    }
+                    Action::ReadOnlyArchive => {
+                        self.error = String::from("Archives are read-only, extract them first")
+                    }
 
                     // Input Mode Switch Handling
                     Action::NavigationInputMode => {
@@ -53,6 +351,7 @@ impl FileManager {
                         self.input.reset();
                     }
                     Action::CommandInputMode => self.input_mode = InputMode::Command,
+                    Action::ToggleGallery => self.toggle_gallery(),
 
                     // Miscellaneous
                     Action::Enter => {
@@ -66,12 +365,63 @@ impl FileManager {
                                 self.input_mode = InputMode::Confirmation(Rename)
                             }
 
+                            InputMode::Operation(Transform) => {
+                                match is_valid_transform(self.input.value()) {
+                                    Ok(()) => self.input_mode = InputMode::Confirmation(Transform),
+                                    Err(e) => {
+                                        self.error = e;
+                                        self.input.reset();
+                                        self.input_mode = InputMode::Navigation;
+                                    }
+                                }
+                            }
+
+                            InputMode::Operation(Compress) => {
+                                let archive_name = Path::new(self.input.value().trim());
+
+                                if ArchiveKind::from_path(archive_name).is_some() {
+                                    self.input_mode = InputMode::Confirmation(Compress);
+                                } else {
+                                    self.error = String::from(
+                                        "Archive name must end in .zip, .tar, .tar.gz, .tar.xz or .tar.zst",
+                                    );
+                                    self.input.reset();
+                                    self.input_mode = InputMode::Navigation;
+                                }
+                            }
+
+                            InputMode::Operation(Copy) => {
+                                self.input_mode = InputMode::Confirmation(Copy)
+                            }
+
+                            InputMode::Operation(Move) => {
+                                self.input_mode = InputMode::Confirmation(Move)
+                            }
+
+                            InputMode::Operation(Columns) => {
+                                self.set_columns();
+                                self.input_mode = InputMode::Navigation;
+                            }
+
+                            InputMode::Command => {
+                                self.run_command();
+                                self.input_mode = InputMode::Navigation;
+                            }
+
                             // Operation Confirmation
                             InputMode::Confirmation(op) => {
                                 match op {
                                     Add => self.create_file(),
                                     Rename => self.rename_file(),
-                                    Delete => self.remove_file(),
+                                    Delete => self.remove_selection(),
+                                    Transform => self.transform_images(),
+                                    Compress => self.compress_selection(),
+                                    Extract => self.extract_archive(true),
+                                    ExtractHere => self.extract_archive(false),
+                                    // set straight from their prompt
+                                    Columns => {}
+                                    Copy => self.transfer_selection(true),
+                                    Move => self.transfer_selection(false),
                                 }
                                 self.input_mode = InputMode::Navigation;
                             }
@@ -82,8 +432,10 @@ impl FileManager {
                     Action::InputChar => {
                         self.input.handle_event(&event);
                     }
+                    Action::ConfirmChoice => self.confirm_choice(),
                     Action::CloseMessage => self.error = String::new(),
                     Action::Quit => self.exit(),
+                    Action::QuitAndCd => self.exit_and_cd(),
 
                     _ => {}
                 }
@@ -95,33 +447,267 @@ impl FileManager {
 
     fn handle_key_event(&mut self, key: KeyEvent) -> Action {
         match &self.input_mode {
+            InputMode::Navigation if self.gallery => match key.code {
+                // Gallery Controls
+                KeyCode::Char('h') | KeyCode::Left => Action::GalleryLeft,
+                KeyCode::Char('l') | KeyCode::Right => Action::GalleryRight,
+                KeyCode::Char('k') | KeyCode::Up => Action::GalleryUp,
+                KeyCode::Char('j') | KeyCode::Down => Action::GalleryDown,
+                KeyCode::Char('v') | KeyCode::Enter | KeyCode::Esc => Action::ToggleGallery,
+
+                // Operation Controls
+                KeyCode::Char('r' | 'd' | 'i' | ' ') if self.is_in_archive() => {
+                    Action::ReadOnlyArchive
+                }
+                KeyCode::Char('r') if self.is_hovering() => Action::RenameFile,
+                KeyCode::Char('d') if self.is_hovering() => Action::DeleteFile,
+                KeyCode::Char('i') => Action::TransformImages,
+                KeyCode::Char(' ') => Action::ToggleMark,
+                KeyCode::Char('u') => Action::ClearMarks,
+
+                // Miscellaneous Controls
+                KeyCode::Char('x') => Action::CloseMessage,
+                KeyCode::Char('q') => Action::Quit,
+                KeyCode::Char('Q') => Action::QuitAndCd,
+                _ => Action::None,
+            },
+
+            InputMode::Navigation if self.grep.is_some() => match key.code {
+                // Content Search Controls
+                KeyCode::Char('j') | KeyCode::Down => Action::GrepNext,
+                KeyCode::Char('k') | KeyCode::Up => Action::GrepPrevious,
+                KeyCode::Char('l') | KeyCode::Enter | KeyCode::Right => {
+                    Action::OpenGrepMatch(false)
+                }
+                KeyCode::Char('e') => Action::OpenGrepMatch(true),
+                KeyCode::Char('G') => Action::StartGrepInput,
+                KeyCode::Char('h') | KeyCode::Left | KeyCode::Esc => Action::CloseGrep,
+
+                // Miscellaneous Controls
+                KeyCode::Char('x') => Action::CloseMessage,
+                KeyCode::Char('q') => Action::Quit,
+                KeyCode::Char('Q') => Action::QuitAndCd,
+                _ => Action::None,
+            },
+
             InputMode::Navigation => match key.code {
+                // Chooser Controls
+                KeyCode::Char('l') | KeyCode::Enter | KeyCode::Right
+                    if self.is_hovered_choosable() =>
+                {
+                    Action::ConfirmChoice
+                }
+                KeyCode::Char('s')
+                    if self.is_chooser() && key.modifiers.contains(KeyModifiers::CONTROL) =>
+                {
+                    Action::ConfirmChoice
+                }
+
                 // Navigation Controls
                 KeyCode::Char('j') | KeyCode::Down => Action::NextItem,
                 KeyCode::Char('k') | KeyCode::Up => Action::PreviousItem,
                 KeyCode::Char('l') | KeyCode::Enter | KeyCode::Right => Action::EnterItem,
                 KeyCode::Char('h') | KeyCode::Backspace | KeyCode::Left => Action::ExitItem,
 
+                // History Controls, terminals send Ctrl-i as Tab
+                KeyCode::Char('o') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    Action::GoBack
+                }
+                KeyCode::Char('i') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    Action::GoForward
+                }
+                KeyCode::Tab => Action::GoForward,
+
                 // Input Mode Controls
-                KeyCode::Char('!') => Action::CommandInputMode,
+                KeyCode::Char('!') | KeyCode::Char(':') => Action::CommandInputMode,
+                KeyCode::Char('v') => Action::ToggleGallery,
 
                 // Operation Controls
+                KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::ALT) => {
+                    Action::ToggleSortCase
+                }
+                KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::ALT) => {
+                    Action::ToggleDirectoriesFirst
+                }
+                KeyCode::Char('a' | 'r' | 'd' | 'i' | 'c' | 'e' | 'E' | 'y' | 'M' | ' ')
+                    if self.is_in_archive() =>
+                {
+                    Action::ReadOnlyArchive
+                }
                 KeyCode::Char('a') => Action::AddFile,
                 KeyCode::Char('r') if self.is_hovering() => Action::RenameFile,
                 KeyCode::Char('d') if self.is_hovering() => Action::DeleteFile,
+                KeyCode::Char('i') => Action::TransformImages,
+                KeyCode::Char('c') => Action::CompressSelection,
+                KeyCode::Char('y') => Action::CopySelection,
+                KeyCode::Char('M') => Action::MoveSelection,
+                KeyCode::Char('e') if self.is_hovering() => Action::ExtractArchive(true),
+                KeyCode::Char('E') if self.is_hovering() => Action::ExtractArchive(false),
+                KeyCode::Char(' ') => Action::ToggleMark,
+                KeyCode::Char('u') => Action::ClearMarks,
+                KeyCode::Char('A') => Action::MarkAll,
+                KeyCode::Char('C') => Action::EditColumns,
+                KeyCode::Char('s') => Action::CycleSort,
+                KeyCode::Char('S') => Action::ReverseSort,
+                KeyCode::Char('.') => Action::ToggleHidden,
+                KeyCode::Char('I') => Action::CycleIgnoreMode,
+
+                // Search Controls
+                KeyCode::Char('/') => Action::StartSearch(false),
+                KeyCode::Char('?') => Action::StartSearch(true),
+                KeyCode::Char('n') => Action::NextMatch,
+                KeyCode::Char('N') => Action::PreviousMatch,
+
+                // Find Controls
+                KeyCode::Char('f') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    Action::StartFindInput
+                }
+
+                // Filter Controls
+                KeyCode::Char('f') => Action::StartFilter,
+                KeyCode::Esc if self.current_filter().is_some() => Action::ClearFilter,
+                KeyCode::Esc if self.is_showing_find_results() => Action::CloseFindResults,
+
+                // Finder Controls
+                KeyCode::Char('F') => Action::StartFinder,
+                KeyCode::Char('G') => Action::StartGrepInput,
+
+                // Bookmark Controls
+                KeyCode::Char('m') => Action::StartSetBookmark,
+                KeyCode::Char('\'') => Action::StartJumpToBookmark,
+                KeyCode::Char('B') => Action::OpenBookmarkPicker,
+
+                // Tab Controls
+                KeyCode::Char('t') => Action::NewTab,
+                KeyCode::Char('w') => Action::CloseTab,
+                KeyCode::Char(']') => Action::CycleTab(false),
+                KeyCode::Char('[') => Action::CycleTab(true),
+                KeyCode::Char('}') => Action::MoveTab(false),
+                KeyCode::Char('{') => Action::MoveTab(true),
+                KeyCode::Char(digit @ '1'..='9') => {
+                    Action::SwitchTab(digit as usize - '1' as usize)
+                }
+
+                // Jump Controls
+                KeyCode::Char('z') => Action::StartJumpInput,
+                KeyCode::Char('g') => Action::StartGoTo,
 
                 // Miscellaneous Controls
                 KeyCode::Char('x') => Action::CloseMessage,
                 KeyCode::Char('q') | KeyCode::Esc => Action::Quit,
+                KeyCode::Char('Q') => Action::QuitAndCd,
                 _ => Action::None,
             },
 
-            InputMode::Operation(Add) | InputMode::Operation(Rename) => match key.code {
+            InputMode::Operation(Copy) | InputMode::Operation(Move) => match key.code {
+                KeyCode::Esc => Action::NavigationInputMode,
+                KeyCode::Enter => Action::Enter,
+                KeyCode::Char(digit @ '1'..='9') if key.modifiers.contains(KeyModifiers::ALT) => {
+                    Action::FillTabDestination(digit as usize - '1' as usize)
+                }
+                _ => Action::InputChar,
+            },
+
+            InputMode::Operation(Add)
+            | InputMode::Operation(Rename)
+            | InputMode::Operation(Transform)
+            | InputMode::Operation(Compress)
+            | InputMode::Operation(Columns)
+            | InputMode::Command => match key.code {
                 KeyCode::Esc => Action::NavigationInputMode,
                 KeyCode::Enter => Action::Enter,
                 _ => Action::InputChar,
             },
 
+            InputMode::Search => match key.code {
+                KeyCode::Esc => Action::CancelSearch,
+                KeyCode::Enter => Action::FinishSearch,
+                KeyCode::Tab => Action::ToggleSearchRegex,
+                _ => Action::SearchInput,
+            },
+
+            InputMode::Filter => match key.code {
+                KeyCode::Esc => Action::ClearFilter,
+                KeyCode::Enter => Action::FinishFilter,
+                _ => Action::FilterInput,
+            },
+
+            InputMode::SetBookmark => match key.code {
+                KeyCode::Char(key) => Action::SetBookmark(key),
+                _ => Action::NavigationInputMode,
+            },
+
+            InputMode::JumpToBookmark => match key.code {
+                KeyCode::Char(key) => Action::JumpToBookmark(key),
+                _ => Action::NavigationInputMode,
+            },
+
+            InputMode::Bookmarks => match key.code {
+                KeyCode::Esc => Action::CloseBookmarkPicker,
+                KeyCode::Enter => Action::OpenPickedBookmark,
+                KeyCode::Down => Action::BookmarkNext,
+                KeyCode::Up => Action::BookmarkPrevious,
+                KeyCode::Char('n' | 'j') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    Action::BookmarkNext
+                }
+                KeyCode::Char('p' | 'k') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    Action::BookmarkPrevious
+                }
+                KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    Action::RemovePickedBookmark
+                }
+                KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    Action::StartNamingPickedBookmark
+                }
+                _ => Action::BookmarkInput,
+            },
+
+            InputMode::NameBookmark(_) => match key.code {
+                KeyCode::Esc => Action::CancelNamingBookmark,
+                KeyCode::Enter => Action::NameBookmark,
+                _ => Action::InputChar,
+            },
+
+            InputMode::Find => match key.code {
+                KeyCode::Esc => Action::NavigationInputMode,
+                KeyCode::Enter => Action::StartFind,
+                _ => Action::InputChar,
+            },
+
+            InputMode::Jump => match key.code {
+                KeyCode::Esc => Action::NavigationInputMode,
+                KeyCode::Enter => Action::Jump,
+                _ => Action::InputChar,
+            },
+
+            InputMode::GoTo => match key.code {
+                KeyCode::Esc => Action::CancelGoTo,
+                KeyCode::Enter => Action::GoTo,
+                KeyCode::Tab => Action::CompleteNext,
+                KeyCode::BackTab => Action::CompletePrevious,
+                _ => Action::GoToInput,
+            },
+
+            InputMode::Grep => match key.code {
+                KeyCode::Esc => Action::NavigationInputMode,
+                KeyCode::Enter => Action::StartGrep,
+                _ => Action::InputChar,
+            },
+
+            InputMode::Finder => match key.code {
+                KeyCode::Esc => Action::CloseFinder,
+                KeyCode::Enter => Action::OpenFinderMatch,
+                KeyCode::Down => Action::FinderNext,
+                KeyCode::Up => Action::FinderPrevious,
+                KeyCode::Char('n' | 'j') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    Action::FinderNext
+                }
+                KeyCode::Char('p' | 'k') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    Action::FinderPrevious
+                }
+                _ => Action::FinderInput,
+            },
+
             InputMode::Operation(Delete) | InputMode::Confirmation(_) => match key.code {
                 KeyCode::Esc | KeyCode::Char('n') => Action::NavigationInputMode,
                 KeyCode::Enter | KeyCode::Char('y') => Action::Enter,
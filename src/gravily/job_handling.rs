@@ -0,0 +1,82 @@
+use super::FileManager;
+use crate::gravily::{Job, JobUpdate};
+
+use ratatui::{style::Stylize, text::Line};
+
+use std::sync::mpsc::{Sender, TryRecvError, channel};
+use std::thread;
+
+impl FileManager {
+    // Runs `work` on its own thread. It reports every finished item through the
+    // sender, and the job ends once the sender is dropped.
+    pub fn spawn_job<F>(&mut self, description: String, total: usize, work: F)
+    where
+        F: FnOnce(Sender<JobUpdate>) + Send + 'static,
+    {
+        let (sender, updates) = channel();
+        thread::spawn(move || work(sender));
+
+        self.jobs.push(Job {
+            description,
+            done: 0,
+            total,
+            errors: Vec::new(),
+            updates,
+            finished: false,
+        });
+    }
+
+    pub fn poll_jobs(&mut self) {
+        for job in self.jobs.iter_mut() {
+            loop {
+                match job.updates.try_recv() {
+                    Ok(JobUpdate::Total(total)) => job.total = total,
+                    Ok(JobUpdate::Done(result)) => {
+                        job.done += 1;
+                        if let Err(e) = result {
+                            job.errors.push(e);
+                        }
+                    }
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        job.finished = true;
+                        break;
+                    }
+                }
+            }
+        }
+
+        for job in self.jobs.iter().filter(|job| job.finished) {
+            if !job.errors.is_empty() {
+                self.error = format!("{}: {}", job.description, job.errors.join("; "));
+            }
+        }
+
+        // copies and moves can take find results away
+        if self.jobs.iter().any(|job| job.finished) {
+            self.prune_find_results();
+        }
+
+        self.jobs.retain(|job| !job.finished);
+    }
+
+    pub fn job_status(&self) -> Option<Line<'static>> {
+        if self.jobs.is_empty() {
+            return None;
+        }
+
+        let mut spans = vec![" ".into()];
+        for job in &self.jobs {
+            spans.push(format!("{} ", job.description).into());
+
+            // tar archives only tell how many entries they have once fully read
+            let progress = match job.total {
+                0 => format!("{} ", job.done),
+                total => format!("{}/{} ", job.done, total),
+            };
+            spans.push(progress.yellow().bold());
+        }
+
+        Some(Line::from(spans))
+    }
+}
@@ -1,26 +1,45 @@
 use ratatui::{
     DefaultTerminal, Frame,
     layout::{Constraint, Layout},
+    text::Line,
     widgets::ListState,
 };
 
+use globset::GlobMatcher;
+use ignore::gitignore::Gitignore;
 use image::DynamicImage;
+use ratatui_image::protocol::StatefulProtocol;
 
+use regex::Regex;
+use serde::Deserialize;
 use tui_input::Input;
 
-use std::env::var;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::env::{current_dir, var};
 use std::ffi::OsString;
+use std::fmt;
+use std::fs::File;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, Sender};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime};
 
-use crate::gravily::OperationType::{Add, Delete, Rename};
-use whoami::DesktopEnv;
+use crate::gravily::OperationType::{Add, Columns, Compress, Copy, Move, Rename, Transform};
+use crate::gravily::image_handling::detect_graphics_protocol;
 
 #[derive(Debug, PartialEq)]
 pub enum OperationType {
     Add,
     Delete,
     Rename,
+    Transform,
+    Compress,
+    Extract,
+    ExtractHere,
+    Columns,
+    Copy,
+    Move,
 }
 
 #[derive(Debug, Default, PartialEq)]
@@ -30,48 +49,588 @@ enum InputMode {
     Command,
     Operation(OperationType),
     Confirmation(OperationType),
+    Search,
+    Filter,
+    Finder,
+    Grep,
+    Find,
+    SetBookmark,
+    JumpToBookmark,
+    Bookmarks,
+    // renaming the bookmark at this index, picked in the bookmark picker
+    NameBookmark(usize),
+    Jump,
+    GoTo,
 }
 
 #[derive(Debug, Default)]
 pub struct FileManager {
     path: PathBuf,
     path_items: Vec<OsString>,
-    past_states: Vec<usize>,
+    back_history: Vec<PathBuf>,
+    forward_history: Vec<PathBuf>,
+    last_selected: HashMap<PathBuf, OsString>,
     input_mode: InputMode,
     input: Input,
     error: String,
     exit: bool,
     state: ListState,
+    image_loader: Option<ImageLoader>,
+    archive_loader: Option<ArchiveLoader>,
+    image_info: Option<ImageInfo>,
+    next_frame: Option<Instant>,
+    gallery: bool,
+    gallery_columns: usize,
+    gallery_scroll: usize,
+    marked: BTreeSet<PathBuf>,
+    jobs: Vec<Job>,
+    config: Config,
+    archive: Option<ArchiveView>,
+    columns: Vec<Column>,
+    sort: Sort,
+    listing_cache: Option<ListingCache>,
+    text_preview: Option<TextPreview>,
+    dir_preview: Option<DirPreview>,
+    show_hidden: bool,
+    ignore_mode: IgnoreMode,
+    ignore_rules: Option<IgnoreRules>,
+    ignored_items: BTreeSet<OsString>,
+    search: Option<Search>,
+    filters: HashMap<PathBuf, Filter>,
+    finder: Option<Finder>,
+    grep: Option<Grep>,
+    editor_request: Option<(PathBuf, usize)>,
+    find_results: Option<FindResults>,
+    bookmarks: Vec<Bookmark>,
+    bookmark_picker: Option<ListState>,
+    frecency: Vec<Frecency>,
+    // ranks added since the frecency file was read, merged into what's on
+    // disk when saving so other instances' visits aren't lost
+    frecency_visits: Vec<Frecency>,
+    completion: Option<Completion>,
+    options: Options,
+    cd_on_exit: bool,
+    chosen: Vec<PathBuf>,
+    // the stdout the chosen paths go to once the screen was moved off it
+    chooser_output: Option<File>,
+    // every tab but the shown one, whose slot holds a placeholder and whose
+    // state lives in the fields above. Empty until a second tab is opened
+    tabs: Vec<Tab>,
+    active_tab: usize,
 }
 
-pub struct ImageWidget {
-    img: DynamicImage,
+// What each tab keeps for itself while another one is shown
+#[derive(Debug, Default)]
+pub struct Tab {
+    path: PathBuf,
+    archive: Option<ArchiveView>,
+    state: ListState,
+    back_history: Vec<PathBuf>,
+    forward_history: Vec<PathBuf>,
+    last_selected: HashMap<PathBuf, OsString>,
+    filters: HashMap<PathBuf, Filter>,
+    sort: Sort,
+    find_results: Option<FindResults>,
+}
+
+// What was asked for on the command line, paths are absolute once parsed
+#[derive(Debug, Default)]
+pub struct Options {
+    // directory to open, or a file to select in its directory
+    start: Option<PathBuf>,
+    config: Option<PathBuf>,
+    show_hidden: bool,
+    // where `Q` leaves the directory it quit from
+    choosedir: Option<PathBuf>,
+    // picking files for another program, written newline separated to
+    // `chooser_file` (stdout when it's "-" or left out) on the way out
+    chooser_file: Option<PathBuf>,
+    chooser_multiple: bool,
+    chooser_dir: bool,
+}
+
+// A place saved in the bookmarks file, `key` jumps to it with '<key>
+#[derive(Debug)]
+pub struct Bookmark {
+    name: String,
+    path: PathBuf,
+    key: Option<char>,
+}
+
+// Directory names completing the go-to prompt, each one is `base` followed
+// by the name
+#[derive(Debug)]
+pub struct Completion {
+    base: String,
+    names: Vec<String>,
+    state: ListState,
+}
+
+// A visited directory, `rank` grows with every visit and decays over time,
+// saved in the frecency file
+#[derive(Debug)]
+pub struct Frecency {
+    path: PathBuf,
+    rank: f64,
+    last_access: u64,
+}
+
+// Entries below `root` that matched a `find` query, listed instead of the
+// directory's own entries while `path` is `root`. Their names are paths
+// relative to `root`, `walker` is None once the walk is done
+#[derive(Debug)]
+pub struct FindResults {
+    root: PathBuf,
+    query: String,
+    entries: Vec<SortEntry>,
+    walker: Option<Receiver<Vec<SortEntry>>>,
+}
+
+// Any of the groups has to match, with every term in it
+#[derive(Debug)]
+pub struct FindQuery {
+    groups: Vec<Vec<FindTerm>>,
+}
+
+#[derive(Debug)]
+pub struct FindTerm {
+    negated: bool,
+    predicate: Predicate,
+}
+
+#[derive(Debug)]
+pub enum Predicate {
+    Size(Comparison, u64),
+    // compared against how long ago the entry was modified
+    Modified(Comparison, Duration),
+    Type(EntryKind),
+    // '+' any of the bits are set, '-' none of them, '=' exactly these
+    Permissions(char, u32),
+    Owner(String),
+    Extension(String),
+    Name(GlobMatcher),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Comparison {
+    Less,
+    AtMost,
+    Equal,
+    AtLeast,
+    Greater,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum EntryKind {
+    File,
+    Dir,
+    Symlink,
+}
+
+// The fuzzy finder popup, candidates are paths relative to `root` found by a
+// walk on another thread, `walker` is None once it's done
+#[derive(Debug)]
+pub struct Finder {
+    root: PathBuf,
+    candidates: Vec<PathBuf>,
+    matches: Vec<FinderMatch>,
+    walker: Option<Receiver<Vec<PathBuf>>>,
+    state: ListState,
+}
+
+// Results of a search through the contents of every file below `root`,
+// files with matches arrive through `searcher` until it's done
+#[derive(Debug)]
+pub struct Grep {
+    root: PathBuf,
+    pattern: Regex,
+    files: Vec<GrepFile>,
+    searcher: Option<Receiver<GrepFile>>,
+    state: ListState,
+    preview: Option<GrepPreview>,
+}
+
+// A line of the results list, indexes into `files`, their `matches` and the
+// matches' `context`
+#[derive(Debug, Clone, Copy)]
+pub enum GrepRow {
+    File(usize),
+    Match(usize, usize),
+    Context(usize, usize, usize),
+}
+
+// The preview pane's lines for the selected match, kept until another one is
+// selected
+#[derive(Debug)]
+pub struct GrepPreview {
+    path: PathBuf,
+    line_number: usize,
+    height: usize,
+    lines: Vec<Line<'static>>,
+}
+
+// `path` is relative to the search's root
+#[derive(Debug)]
+pub struct GrepFile {
+    path: PathBuf,
+    matches: Vec<GrepMatch>,
+}
+
+// `context` holds numbered lines around the match, ones it shares with the
+// match before it are only kept there
+#[derive(Debug)]
+pub struct GrepMatch {
+    line_number: usize,
+    line: String,
+    context: Vec<(usize, String)>,
+}
+
+// A candidate that fits the query, `positions` are the matched characters
+#[derive(Debug)]
+pub struct FinderMatch {
+    index: usize,
+    score: i64,
+    positions: Vec<usize>,
+}
+
+// Narrows a directory's listing to matching names, kept per directory for
+// the whole session
+#[derive(Debug)]
+pub struct Filter {
+    query: String,
+    glob: Option<GlobMatcher>,
+    case_sensitive: bool,
+}
+
+// The last `/` or `?` search, `pattern` is None while the query is empty or
+// isn't a valid regex
+#[derive(Debug)]
+pub struct Search {
+    query: String,
+    pattern: Option<Regex>,
+    backward: bool,
+    use_regex: bool,
+    origin: usize,
+}
+
+// An archive browsed like a directory, `path` points inside it as
+// archive_path/inner_path while it's open
+#[derive(Debug)]
+pub struct ArchiveView {
+    archive_path: PathBuf,
+    kind: ArchiveKind,
+    entries: Vec<ArchiveEntry>,
+    inner_path: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+    TarXz,
+    TarZst,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchiveEntry {
+    path: PathBuf,
+    index: usize,
+    size: u64,
+    // only zip stores entries compressed on their own
+    compressed_size: Option<u64>,
+    is_dir: bool,
+}
+
+// Work for the archive loader's thread: reading the table of contents of the
+// hovered archive, or extracting a file inside the open one for its preview
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArchiveRequest {
+    List {
+        path: PathBuf,
+        modified: Option<SystemTime>,
+        kind: ArchiveKind,
+    },
+    Extract(ExtractRequest),
+}
+
+#[derive(Debug)]
+pub enum ArchiveResult {
+    Listed(ArchiveContents),
+    Extracted(ExtractRequest, Result<PathBuf, String>),
+}
+
+// A file inside the open archive to extract for its preview, `path` is where
+// the listing shows it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtractRequest {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    archive_path: PathBuf,
+    kind: ArchiveKind,
+    entry: ArchiveEntry,
+}
+
+// Lists and extracts archives on a worker thread so huge or slow to decompress
+// ones never hold up a frame. Extracted files go into a directory of its own
+// that's removed when it shuts down.
+pub struct ArchiveLoader {
+    requests: Sender<Vec<ArchiveRequest>>,
+    results: Receiver<ArchiveResult>,
+    worker: JoinHandle<()>,
+    pending: Vec<ArchiveRequest>,
+    wanted: Vec<ArchiveRequest>,
+    listed: VecDeque<ArchiveContents>,
+    extracted: Vec<(ExtractRequest, Result<PathBuf, String>)>,
+}
+
+// Table of contents of a hovered archive, kept so it isn't read again until
+// the archive changes
+#[derive(Debug)]
+pub struct ArchiveContents {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    entries: Result<Vec<ArchiveEntry>, String>,
+    truncated: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    // Output name of transformed images, {name}, {action} and {ext} are
+    // replaced with the original's name, the action taken and the extension
+    image_name_pattern: String,
+    // Metadata shown next to the names, in order
+    columns: Vec<Column>,
+    // Whether dotfiles are listed
+    show_hidden: bool,
+    // What happens to entries matched by .gitignore and .ignore files
+    ignore_rules: IgnoreMode,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IgnoreMode {
+    #[default]
+    Off,
+    Dim,
+    Hide,
+}
+
+// Parsed ignore files that apply to `dir`, the closest one comes first
+#[derive(Debug)]
+pub struct IgnoreRules {
+    dir: PathBuf,
+    matchers: Vec<Gitignore>,
+}
+
+// How the listing is ordered, directories go first unless turned off
+#[derive(Debug, Clone, Copy)]
+pub struct Sort {
+    mode: SortMode,
+    reverse: bool,
+    case_sensitive: bool,
+    directories_first: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortMode {
+    Name,
+    Extension,
+    Size,
+    Modified,
+    Created,
+    Changed,
+    Type,
+}
+
+// What entries are compared by, metadata is only read when the mode needs it
+#[derive(Debug, Clone)]
+pub struct SortEntry {
+    name: OsString,
+    is_dir: bool,
+    kind: u8,
+    size: u64,
+    modified: Option<SystemTime>,
+    created: Option<SystemTime>,
+    // inode change time as seconds and nanoseconds, only unix has it
+    changed: Option<(i64, i64)>,
+}
+
+// The current directory's entries as last read, reused until the directory
+// is modified or sorted by something else
+#[derive(Debug)]
+pub struct ListingCache {
+    path: PathBuf,
+    modified: SystemTime,
+    mode: SortMode,
+    entries: Vec<SortEntry>,
+}
+
+// The previewed directory's entries and ignore rules as last read, reused
+// like the listing until the directory changes
+#[derive(Debug)]
+pub struct DirPreview {
+    path: PathBuf,
+    modified: SystemTime,
+    mode: SortMode,
+    entries: Vec<SortEntry>,
+    rules: Option<IgnoreRules>,
+}
+
+// The start of the previewed file until it changes, `text` is None when it
+// isn't text
+#[derive(Debug)]
+pub struct TextPreview {
+    path: PathBuf,
+    modified: SystemTime,
+    text: Option<String>,
+}
+
+// A piece of metadata that can be shown next to each name in the listing
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Column {
+    Size,
+    Permissions,
+    Owner,
+    Modified,
+    Created,
+    Accessed,
+    LinkTarget,
+    ItemCount,
+}
+
+// Work running on another thread, like converting a batch of images
+#[derive(Debug)]
+pub struct Job {
+    description: String,
+    done: usize,
+    total: usize,
+    errors: Vec<String>,
+    updates: Receiver<JobUpdate>,
+    finished: bool,
+}
+
+#[derive(Debug)]
+pub enum JobUpdate {
+    // Sent when the number of items is only known once the job started
+    Total(usize),
+    Done(Result<(), String>),
+}
+
+pub struct ImageWidget<'a> {
+    img: &'a DynamicImage,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageKey {
+    path: PathBuf,
+    modified: SystemTime,
+    size: (u16, u16),
+}
+
+// Format, dimensions and EXIF fields of the hovered image, read once per file
+#[derive(Debug)]
+pub struct ImageInfo {
+    path: PathBuf,
+    modified: SystemTime,
+    fields: Vec<(&'static str, String)>,
+}
+
+pub enum CachedImage {
+    HalfBlocks(DynamicImage),
+    Protocol(StatefulProtocol),
+    Animation(Animation),
+}
+
+// Frames of an animated GIF, APNG or WebP with their delays, playback is paused
+// (shown_at is None) while the image isn't on screen
+pub struct Animation {
+    frames: Vec<(CachedImage, Duration)>,
+    current: usize,
+    shown_at: Option<Instant>,
+}
+
+// Decodes previews on a worker thread and keeps the most recently shown ones,
+// failed decodes are cached as None so they aren't retried on every redraw
+pub struct ImageLoader {
+    requests: Sender<Vec<ImageKey>>,
+    results: Receiver<(ImageKey, Option<CachedImage>)>,
+    pending: Vec<ImageKey>,
+    wanted: Vec<ImageKey>,
+    shown: Vec<ImageKey>,
+    cache: VecDeque<(ImageKey, Option<CachedImage>)>,
 }
 
 impl FileManager {
+    pub fn new(options: Options) -> Self {
+        Self {
+            options,
+            ..Self::default()
+        }
+    }
+
     pub fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
-        if whoami::desktop_env() == DesktopEnv::Windows {
-            match var("USERPROFILE") {
-                Ok(default_path) => self.path = PathBuf::from(default_path),
-                Err(e) => eprintln!("Couldn't get USERPROFILE variable: {}", e),
-            }
-        } else {
-            // assuming linux
-            self.path = PathBuf::from("/home/");
-            self.path.push("/home/");
-
-            match var("USER") {
-                Ok(user) => self.path.push(user),
-                Err(e) => eprintln!("Couldn't get USER variable: {}", e),
-            }
+        let start = self.options.start.clone();
+        self.path = match &start {
+            Some(start) if start.is_dir() => start.clone(),
+            Some(start) => start.parent().map(Path::to_path_buf).unwrap_or_default(),
+            None => default_start_dir(),
+        };
+
+        match Config::load(self.options.config.as_deref()) {
+            Ok(config) => self.config = config,
+            Err(e) => self.error = e,
         }
+        self.columns = self.config.columns.clone();
+        self.show_hidden = self.config.show_hidden || self.options.show_hidden;
+        self.ignore_mode = self.config.ignore_rules;
+        self.load_bookmarks();
+        self.load_frecency();
+        self.record_visit(&self.path.clone());
 
-        self.state.select_first();
+        self.image_loader = Some(ImageLoader::new(detect_graphics_protocol()));
+        self.archive_loader = Some(ArchiveLoader::new());
+        match start {
+            Some(start) if !start.is_dir() => self.reveal_path(&start),
+            _ => self.state.select_first(),
+        }
 
         while !self.exit {
+            if let Some(image_loader) = &mut self.image_loader {
+                image_loader.receive();
+            }
+            if let Some(archive_loader) = &mut self.archive_loader {
+                archive_loader.receive();
+            }
+            self.poll_jobs();
+            self.poll_finder();
+            self.poll_grep();
+            self.poll_find();
+
             terminal.draw(|frame| self.draw(frame))?;
+
+            self.next_frame = self
+                .image_loader
+                .as_mut()
+                .and_then(ImageLoader::finish_frame);
+            if let Some(archive_loader) = &mut self.archive_loader {
+                archive_loader.finish_frame();
+            }
+
             self.handle_events()?;
+
+            if let Some((path, line_number)) = self.editor_request.take() {
+                self.open_in_editor(terminal, &path, line_number);
+            }
         }
+
+        self.save_frecency();
+        if let Some(archive_loader) = self.archive_loader.take() {
+            archive_loader.shut_down();
+        }
+
         Ok(())
     }
 
@@ -89,13 +648,27 @@ impl FileManager {
         }
 
         match &self.input_mode {
-            InputMode::Navigation => {
-                if !self.error.is_empty() {
-                    self.render_error_text(frame, horizontal_area[1]);
-                }
+            InputMode::Navigation if !self.error.is_empty() => {
+                self.render_error_text(frame, horizontal_area[1]);
             }
 
-            InputMode::Operation(Add) | InputMode::Operation(Rename) => {
+            InputMode::Operation(Add)
+            | InputMode::Operation(Rename)
+            | InputMode::Operation(Transform)
+            | InputMode::Operation(Compress)
+            | InputMode::Operation(Columns)
+            | InputMode::Operation(Copy)
+            | InputMode::Operation(Move)
+            | InputMode::Command
+            | InputMode::Search
+            | InputMode::Filter
+            | InputMode::Finder
+            | InputMode::Grep
+            | InputMode::Find
+            | InputMode::Jump
+            | InputMode::GoTo
+            | InputMode::Bookmarks
+            | InputMode::NameBookmark(_) => {
                 self.render_cursor(frame, horizontal_area[1]);
                 self.render_input_text(frame, horizontal_area[1]);
             }
@@ -104,20 +677,76 @@ impl FileManager {
                 self.render_confirmation_text(frame, horizontal_area[1]);
             }
 
+            InputMode::SetBookmark | InputMode::JumpToBookmark => {
+                self.render_bookmark_prompt(frame, horizontal_area[1]);
+            }
+
             _ => {}
         }
 
         let list_area = horizontal_area[0];
-        frame.render_widget(self, list_area);
+        frame.render_widget(&mut *self, list_area);
+        self.render_completion(frame, list_area);
     }
 }
 
-impl ImageWidget {
-    pub fn new(img: DynamicImage) -> Self {
+// The directory gravily was started from, or home when that's gone
+fn default_start_dir() -> PathBuf {
+    current_dir()
+        .ok()
+        .or_else(|| var("HOME").or_else(|_| var("USERPROFILE")).ok().map(PathBuf::from))
+        .unwrap_or_else(|| PathBuf::from("/"))
+}
+
+impl<'a> ImageWidget<'a> {
+    pub fn new(img: &'a DynamicImage) -> Self {
         Self { img }
     }
 }
 
+impl fmt::Debug for ImageLoader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ImageLoader")
+            .field("pending", &self.pending)
+            .field("cached", &self.cache.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Debug for ArchiveLoader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArchiveLoader")
+            .field("pending", &self.pending)
+            .field("listed", &self.listed.len())
+            .field("extracted", &self.extracted.len())
+            .finish_non_exhaustive()
+    }
+}
+
+mod archive_handling;
+mod bookmark_handling;
+mod chooser_handling;
+mod column_handling;
+mod command_handling;
+mod compression_handling;
+mod config;
+mod filter_handling;
+mod find_handling;
+mod finder_handling;
+mod frecency_handling;
+mod gallery_handling;
+mod goto_handling;
+mod grep_handling;
 mod helper_functions;
+mod history_handling;
+mod ignore_handling;
+mod image_handling;
 mod input_handling;
+mod job_handling;
+mod options;
 mod render_handling;
+mod search_handling;
+mod sort_handling;
+mod tab_handling;
+mod transfer_handling;
+mod transform_handling;
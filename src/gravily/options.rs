@@ -0,0 +1,170 @@
+use crate::gravily::Options;
+
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+const USAGE: &str = "\
+Usage: gravily [OPTIONS] [PATH]
+
+Opens PATH, or the current directory when there's none. When PATH is a file
+its directory is opened with the file selected.
+
+Options:
+  -c, --config <FILE>  Read the config from FILE instead of
+                       ~/.config/gravily/config.json
+  -a, --show-hidden    List dotfiles from the start
+      --choosedir <FILE>
+                       Write the current directory to FILE when quitting
+                       with Q, for shells to cd into
+      --chooser-file <FILE>
+                       Pick files for another program: Enter writes the
+                       chosen path to FILE, or stdout when FILE is -
+      --chooser-multiple
+                       Allow choosing every marked file at once
+      --chooser-dir    Choose directories instead of files
+  -V, --version        Print the version and exit
+  -h, --help           Print this help and exit";
+
+impl Options {
+    // Ok(None) when only the help or the version was asked for, it's printed
+    // here. Paths are checked up front so mistakes show up before the screen
+    // is taken over
+    pub fn parse(arguments: impl IntoIterator<Item = OsString>) -> Result<Option<Self>, String> {
+        let mut options = Self::default();
+        let mut arguments = arguments.into_iter();
+        let mut only_paths = false;
+
+        while let Some(argument) = arguments.next() {
+            let text = argument.to_string_lossy().into_owned();
+
+            if only_paths || !text.starts_with('-') {
+                if options.start.is_some() {
+                    return Err(format!("Unexpected argument {:?}", text));
+                }
+                options.start = Some(PathBuf::from(argument));
+                continue;
+            }
+
+            // --config=FILE is the same as --config FILE
+            let (flag, inline_value) = match text.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => {
+                    (flag.to_string(), Some(OsString::from(value)))
+                }
+                _ => (text.to_string(), None),
+            };
+
+            match flag.as_str() {
+                "--" => only_paths = true,
+                "-h" | "--help" => {
+                    println!("{}", USAGE);
+                    return Ok(None);
+                }
+                "-V" | "--version" => {
+                    println!("gravily {}", env!("CARGO_PKG_VERSION"));
+                    return Ok(None);
+                }
+                "-a" | "--show-hidden" => options.show_hidden = true,
+                "-c" | "--config" => {
+                    let value = inline_value
+                        .or_else(|| arguments.next())
+                        .ok_or_else(|| format!("{} needs a file", flag))?;
+                    options.config = Some(PathBuf::from(value));
+                }
+                "--chooser-file" => {
+                    let value = inline_value
+                        .or_else(|| arguments.next())
+                        .ok_or_else(|| format!("{} needs a file", flag))?;
+                    options.chooser_file = Some(PathBuf::from(value));
+                }
+                "--chooser-multiple" => options.chooser_multiple = true,
+                "--chooser-dir" => options.chooser_dir = true,
+                "--choosedir" => {
+                    let value = inline_value
+                        .or_else(|| arguments.next())
+                        .ok_or_else(|| format!("{} needs a file", flag))?;
+                    options.choosedir = Some(PathBuf::from(value));
+                }
+                _ => return Err(format!("Unknown option {:?}", flag)),
+            }
+        }
+
+        if let Some(config) = &options.config
+            && !config.is_file()
+        {
+            return Err(format!("{}: No such config file", config.display()));
+        }
+
+        if let Some(start) = &options.start {
+            let start = start
+                .canonicalize()
+                .map_err(|e| format!("{}: {}", start.display(), e))?;
+            options.start = Some(start);
+        }
+
+        Ok(Some(options))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(arguments: &[&str]) -> Result<Option<Options>, String> {
+        Options::parse(arguments.iter().map(OsString::from))
+    }
+
+    #[test]
+    fn flags_and_values() {
+        let options = parse(&["-a", "--choosedir", "/tmp/dir", "--chooser-file=-"])
+            .unwrap()
+            .unwrap();
+        assert!(options.show_hidden);
+        assert_eq!(options.choosedir, Some(PathBuf::from("/tmp/dir")));
+        assert_eq!(options.chooser_file, Some(PathBuf::from("-")));
+        assert!(!options.chooser_multiple && !options.chooser_dir);
+        assert_eq!(options.start, None);
+
+        let options = parse(&["--chooser-multiple", "--chooser-dir"])
+            .unwrap()
+            .unwrap();
+        assert!(options.chooser_multiple && options.chooser_dir);
+        assert_eq!(options.chooser_file, None);
+    }
+
+    #[test]
+    fn start_path_is_canonical() {
+        let options = parse(&["."]).unwrap().unwrap();
+        assert_eq!(options.start, Some(std::env::current_dir().unwrap()));
+
+        assert!(parse(&["/no/such/place"]).is_err());
+        assert!(parse(&[".", ".."]).is_err());
+    }
+
+    #[test]
+    fn paths_after_double_dash() {
+        let options = parse(&["--", "."]).unwrap().unwrap();
+        assert!(options.start.is_some());
+        assert!(parse(&["--", "-a"]).is_err());
+    }
+
+    #[test]
+    fn bad_arguments() {
+        assert_eq!(parse(&["--nope"]).unwrap_err(), "Unknown option \"--nope\"");
+        assert_eq!(parse(&["-c"]).unwrap_err(), "-c needs a file");
+        assert_eq!(
+            parse(&["--chooser-file"]).unwrap_err(),
+            "--chooser-file needs a file"
+        );
+        assert!(
+            parse(&["--config=/no/such/config.json"])
+                .unwrap_err()
+                .ends_with("No such config file")
+        );
+    }
+
+    #[test]
+    fn help_and_version_stop_parsing() {
+        assert!(parse(&["--version", "--nope"]).unwrap().is_none());
+        assert!(parse(&["-h"]).unwrap().is_none());
+    }
+}
@@ -1,24 +1,30 @@
-use crate::gravily::InputMode;
+use crate::gravily::{ArchiveKind, DirPreview, IgnoreMode, InputMode, SortEntry, TextPreview};
 
 use super::FileManager;
-use crate::gravily::OperationType::{Add, Delete, Rename};
+use crate::gravily::OperationType::{
+    Add, Columns, Compress, Copy, Delete, Extract, ExtractHere, Move, Rename, Transform,
+};
+use crate::gravily::archive_handling::archive_stem;
+use crate::gravily::helper_functions::scroll_offset;
 
 use ratatui::{
     Frame,
     buffer::Buffer,
     layout::Rect,
     style::Stylize,
-    style::{Color, Style},
     symbols::border,
     text::Line,
-    widgets::{Block, List, Paragraph, StatefulWidget, Widget, Wrap, block::Title},
+    widgets::{
+        Block, List, Paragraph, Row, StatefulWidget, Table, TableState, Widget, Wrap, block::Title,
+    },
 };
 
-use crate::gravily::ImageWidget;
-use image::{GenericImageView, Pixel, imageops::FilterType};
+use std::fs::{File, metadata, read_dir};
+use std::io::{self, Read};
+use std::path::Path;
 
-use std::fs::{metadata, read_dir, read_to_string};
-use std::path::PathBuf;
+// More than fits in the preview pane, huge files are never read in full
+const MAX_PREVIEW_BYTES: u64 = 64 * 1024;
 
 impl FileManager {
     pub fn render_cursor(&mut self, frame: &mut Frame, area: Rect) {
@@ -74,13 +80,74 @@ impl FileManager {
                 }
             }
 
-            InputMode::Operation(Delete) | InputMode::Confirmation(Delete) => {
+            InputMode::Confirmation(Transform) => {
+                text = Line::from(format!(
+                    "Are you sure you want to {:?} {}?",
+                    self.input.value().trim(),
+                    self.selection_description()
+                ));
+            }
+
+            InputMode::Confirmation(Compress) => {
+                text = Line::from(format!(
+                    "Are you sure you want to compress {} into \"{}\"?",
+                    self.selection_description(),
+                    self.input.value().trim()
+                ));
+            }
+
+            InputMode::Confirmation(Extract) | InputMode::Confirmation(ExtractHere) => {
                 if let Some(path_val) = self.state.selected() {
                     let file_name = &self.path_items[path_val];
 
-                    text = Line::from(format!("Are you sure you want to delete {:?}?", file_name));
+                    let destination = match (&self.input_mode, archive_stem(Path::new(file_name))) {
+                        (InputMode::Confirmation(Extract), Some(stem)) => {
+                            format!("a new folder {:?}", stem)
+                        }
+                        _ => String::from("the current directory"),
+                    };
+
+                    text = Line::from(format!(
+                        "Are you sure you want to extract {:?} into {}?",
+                        file_name, destination
+                    ));
                 }
             }
+
+            InputMode::Confirmation(Copy) | InputMode::Confirmation(Move) => {
+                let verb = if self.input_mode == InputMode::Confirmation(Copy) {
+                    "copy"
+                } else {
+                    "move"
+                };
+
+                text = Line::from(format!(
+                    "Are you sure you want to {} {} into \"{}\"?",
+                    verb,
+                    self.selection_description(),
+                    self.input.value().trim()
+                ));
+            }
+
+            InputMode::Operation(Delete) | InputMode::Confirmation(Delete) => {
+                // directories are removed with everything in them
+                let paths = self.selected_paths();
+                let dirs = paths
+                    .iter()
+                    .filter(|path| path.is_dir() && !path.is_symlink())
+                    .count();
+                let recursive = match (paths.len(), dirs) {
+                    (_, 0) => "",
+                    (1, _) => " and everything in it",
+                    _ => ", directories with everything in them",
+                };
+
+                text = Line::from(format!(
+                    "Are you sure you want to delete {}{}?",
+                    self.selection_description(),
+                    recursive
+                ));
+            }
             _ => {}
         }
 
@@ -92,6 +159,35 @@ impl FileManager {
         frame.render_widget(confirmation_box, area)
     }
 
+    pub fn render_bookmark_prompt(&mut self, frame: &mut Frame, area: Rect) {
+        let (title, text) = if self.input_mode == InputMode::SetBookmark {
+            (
+                " Bookmark (press a key, Esc to cancel) ",
+                format!("Bookmark {} on...", self.path.display()),
+            )
+        } else {
+            let keys: String = self
+                .bookmarks
+                .iter()
+                .filter_map(|bookmark| bookmark.key)
+                .collect();
+
+            (
+                " Jump to bookmark (press its key, Esc to cancel) ",
+                format!("Bookmarked keys: {}", keys),
+            )
+        };
+
+        let block = Block::bordered().title(title).border_set(border::ROUNDED);
+
+        let prompt_box = Paragraph::new(Line::from(text))
+            .alignment(ratatui::layout::Alignment::Left)
+            .block(block)
+            .wrap(Wrap { trim: true });
+
+        frame.render_widget(prompt_box, area)
+    }
+
     pub fn render_input_text(&mut self, frame: &mut Frame, area: Rect) {
         let mut title = Title::from(" Input ");
         let width = area.width.max(3) - 3;
@@ -109,6 +205,107 @@ impl FileManager {
                     title = Title::from(format!(" Renaming file {:?} into... ", file_name,));
                 }
             }
+
+            InputMode::Operation(Transform) => {
+                title = Title::from(format!(
+                    " Transform {} (rotate 90, flip h|v, resize 50% or WxH, strip, convert png|jpg [quality]|webp (lossless)|bmp|tiff) ",
+                    self.selection_description()
+                ));
+            }
+            InputMode::Search => {
+                let search = self.search.as_ref();
+                let direction = if search.is_some_and(|search| search.backward) {
+                    "backward"
+                } else {
+                    "forward"
+                };
+                let kind = if search.is_some_and(|search| search.use_regex) {
+                    "regex"
+                } else {
+                    "text"
+                };
+
+                title = Title::from(format!(
+                    " Search {} for {} (Tab toggles regex, Enter to keep, Esc to cancel) ",
+                    direction, kind
+                ));
+            }
+
+            InputMode::Finder => {
+                title = Title::from(" Find (fuzzy, ↑↓ to move, Enter to go there, Esc to close) ");
+            }
+
+            InputMode::Bookmarks => {
+                title = Title::from(" Bookmarks matching... (fuzzy) ");
+            }
+
+            InputMode::NameBookmark(index) => {
+                title = Title::from(format!(
+                    " Name the bookmark for {} (Enter to keep, Esc to cancel) ",
+                    self.bookmarks[*index].path.display()
+                ));
+            }
+
+            InputMode::Find => {
+                title = Title::from(
+                    " Find where (size>100M mtime<7d type:symlink perm:+x owner:root ext:log name:*.rs, and, or, !) ",
+                );
+            }
+
+            InputMode::Operation(Copy) => {
+                title = Title::from(format!(
+                    " Copy {} into directory... (Alt-<n> for tab n's) ",
+                    self.selection_description()
+                ));
+            }
+
+            InputMode::Operation(Move) => {
+                title = Title::from(format!(
+                    " Move {} into directory... (Alt-<n> for tab n's) ",
+                    self.selection_description()
+                ));
+            }
+
+            InputMode::Grep => {
+                title = Title::from(" Search file contents for (regex) ");
+            }
+
+            InputMode::GoTo => {
+                title = Title::from(
+                    " Go to (~, $VARIABLES and relative paths work, Tab completes) ",
+                );
+            }
+
+            InputMode::Jump => {
+                title = match self.best_jump(self.input.value()) {
+                    Some(path) => Title::from(format!(" Jump to {} ", path.display())),
+                    None => Title::from(" Jump to... (fragments of a visited directory) "),
+                };
+            }
+
+            InputMode::Filter => {
+                title =
+                    Title::from(" Filter (text or glob like *.rs, Enter to keep, Esc to clear) ");
+            }
+
+            InputMode::Command => {
+                title = Title::from(
+                    " Command (sort name|ext|size|mtime|ctime|created|type [reverse], sort reverse|case|dirs, find <predicates>, z <fragments>, cd <path>, import zoxide) ",
+                );
+            }
+
+            InputMode::Operation(Columns) => {
+                title = Title::from(
+                    " Columns (size, permissions, owner, modified, created, accessed, link_target, item_count) ",
+                );
+            }
+
+            InputMode::Operation(Compress) => {
+                title = Title::from(format!(
+                    " Compress {} into... (name.zip, .tar, .tar.gz, .tar.xz or .tar.zst) ",
+                    self.selection_description()
+                ));
+            }
             _ => {}
         }
 
@@ -126,99 +323,119 @@ impl FileManager {
     }
 
     pub fn render_file_items(&mut self, area: Rect, buf: &mut Buffer) {
-        let path_text = Line::from(vec![
+        let mut path_text = Line::from(vec![
             " Current Path: ".into(),
-            self.path.to_str().unwrap().to_string().yellow(),
+            self.path.display().to_string().yellow(),
             " ".into(),
+            format!("({}) ", self.visibility_description()).dark_gray(),
         ]);
 
+        if self.is_showing_find_results() {
+            path_text.push_span(
+                format!(" find {} ", self.find_description())
+                    .black()
+                    .on_green(),
+            );
+            path_text.push_span(" ");
+        }
+
+        if let Some(filter) = self.current_filter() {
+            path_text.push_span(format!(" filter: {} ", filter.query).black().on_cyan());
+            path_text.push_span(" ");
+        }
+
+        let sort_text =
+            Line::from(format!(" Sorted by {} ", self.sort_description())).right_aligned();
+
         let block = Block::bordered()
             .title(path_text)
+            .title_bottom(sort_text)
             .border_set(border::ROUNDED);
 
-        match read_dir(&self.path) {
-            Ok(path_items) => {
-                self.path_items = path_items
-                    .filter_map(|entry_result| {
-                        entry_result
-                            .ok()
-                            .map(|entry| entry.path().file_name().unwrap().to_owned()) // direntry -> pathbuf -> osstr
-                    })
-                    .collect();
-            }
-
-            Err(e) => {
-                self.error = format!("Error entering dir: {:#?}: {}", &self.path, e);
-                self.exit_dir();
-            }
-        }
-
-        let items: Vec<String> = self
-            .path_items
-            .iter()
-            .map(|file_name| String::from(file_name.to_str().unwrap()))
+        self.read_path_items();
+
+        // the header takes a line whenever there are columns
+        let visible_rows = block
+            .inner(area)
+            .height
+            .saturating_sub(!self.columns.is_empty() as u16) as usize;
+
+        // the table only gets the rows on screen, so the selection is kept in
+        // range here instead of by the table
+        let last_item = self.path_items.len().checked_sub(1);
+        let selected = self
+            .state
+            .selected()
+            .zip(last_item)
+            .map(|(selected, last_item)| selected.min(last_item));
+        self.state.select(selected);
+
+        let offset = scroll_offset(self.state.offset(), selected, visible_rows);
+        let end = (offset + visible_rows).min(self.path_items.len());
+        let visible = offset.min(end)..end;
+
+        let highlight_symbol = ">    ";
+        let width = block
+            .inner(area)
+            .width
+            .saturating_sub(highlight_symbol.len() as u16);
+
+        let (rows, widths, shown_columns) = self.column_rows(visible.clone(), width);
+        let header = self.column_header(shown_columns);
+
+        let rows: Vec<Row> = rows
+            .into_iter()
+            .zip(&self.path_items[visible])
+            .map(|(row, file_name)| {
+                if self.marked.contains(&self.path.join(file_name)) {
+                    row.yellow().bold()
+                } else if self.ignored_items.contains(file_name) {
+                    row.dark_gray()
+                } else {
+                    row
+                }
+            })
             .collect();
 
-        let list = List::new(items)
+        let mut table = Table::new(rows, widths)
             .block(block)
-            .highlight_symbol(">    ")
+            .highlight_symbol(highlight_symbol)
             .highlight_spacing(ratatui::widgets::HighlightSpacing::Always);
 
-        StatefulWidget::render(list, area, buf, &mut self.state);
+        if let Some(header) = header {
+            table = table.header(header.bold());
+        }
+
+        let mut table_state =
+            TableState::default().with_selected(selected.map(|selected| selected - offset));
+
+        StatefulWidget::render(table, area, buf, &mut table_state);
+
+        *self.state.offset_mut() = offset;
     }
 
     pub fn render_peekable_items(&mut self, area: Rect, buf: &mut Buffer) {
         let cur_path = self.get_hovered_dir();
-        if cur_path != PathBuf::from(&self.path) {
+        if cur_path != self.path {
             let block = Block::bordered()
                 .title(Line::from(vec![
                     " ".into(),
-                    cur_path.to_str().unwrap().to_string().yellow(),
+                    cur_path.display().to_string().yellow(),
                     " ".into(),
                 ]))
                 .border_set(border::ROUNDED);
 
+            if self.is_in_archive() {
+                self.render_archive_item(&cur_path, block, area, buf);
+                return;
+            }
+
             match metadata(&cur_path) {
                 Ok(metadata) => {
                     if metadata.is_dir() {
-                        match read_dir(&cur_path) {
-                            Ok(path) => {
-                                let items: Vec<String> = path
-                                    .filter_map(|entry_result| entry_result.ok())
-                                    .filter_map(|entry| {
-                                        let path_buf = entry.path();
-
-                                        path_buf
-                                            .strip_prefix(&cur_path)
-                                            .ok()
-                                            .map(|relative_path| {
-                                                relative_path.to_str().map(str::to_owned)
-                                            })
-                                            .flatten()
-                                    })
-                                    .collect();
-                                let list = List::new(items).block(block);
-                                Widget::render(list, area, buf);
-                            }
-                            Err(e) => {
-                                Paragraph::new(format!(
-                                    "Failed to read directory\n > {}\n\nError: {}",
-                                    &cur_path.display(),
-                                    e
-                                ))
-                                .block(block)
-                                .render(area, buf);
-                            }
-                        }
+                        self.render_dir_preview(&cur_path, block, area, buf);
                     } else if metadata.is_file() {
-                        if let Ok(file_text) = read_to_string(&cur_path) {
-                            Paragraph::new(file_text).block(block).render(area, buf);
-                            return;
-                        }
-                        if let Ok(img) = image::open(&cur_path) {
-                            let image_widget = ImageWidget::new(img);
-                            image_widget.render(area, buf);
-                        }
+                        self.render_file_preview(&cur_path, block, area, buf);
                     }
                 }
                 Err(e) => {
@@ -231,35 +448,152 @@ impl FileManager {
             }
         }
     }
-}
 
-impl Widget for ImageWidget {
-    fn render(self, area: Rect, buf: &mut Buffer) {
-        let resized = self.img.resize_exact(
-            area.width as u32,
-            area.height as u32 * 2,
-            FilterType::Nearest,
-        );
-
-        let (_img_width, img_height) = resized.dimensions();
-
-        for y in 0..area.height {
-            for x in 0..area.width {
-                let top_pixel = resized.get_pixel(x as u32, y as u32 * 2).to_rgba();
-                let bottom_pixel = if y as u32 * 2 + 1 < img_height {
-                    resized.get_pixel(x as u32, y as u32 * 2 + 1).to_rgba()
-                } else {
-                    top_pixel
-                };
+    pub fn render_dir_preview(
+        &mut self,
+        dir_path: &Path,
+        block: Block,
+        area: Rect,
+        buf: &mut Buffer,
+    ) {
+        match self.dir_preview_entries(dir_path) {
+            Ok(mut entries) => {
+                // listed the way it will be once it's entered
+                let rules = self
+                    .dir_preview
+                    .as_ref()
+                    .and_then(|preview| preview.rules.as_ref());
+                entries
+                    .retain(|entry| self.is_shown_in(dir_path, rules, &entry.name, entry.is_dir));
+                self.sort.sort(&mut entries);
+
+                let items: Vec<Line> = entries
+                    .iter()
+                    .map(|entry| {
+                        let name = entry.name.to_string_lossy().to_string();
+                        let ignored = self.ignore_mode == IgnoreMode::Dim
+                            && rules
+                                .is_some_and(|rules| rules.is_ignored(&entry.name, entry.is_dir));
+
+                        if ignored {
+                            Line::from(name.dark_gray())
+                        } else {
+                            Line::from(name)
+                        }
+                    })
+                    .collect();
+                let list = List::new(items).block(block);
+                Widget::render(list, area, buf);
+            }
+            Err(e) => {
+                Paragraph::new(format!(
+                    "Failed to read directory\n > {}\n\nError: {}",
+                    dir_path.display(),
+                    e
+                ))
+                .block(block)
+                .render(area, buf);
+            }
+        }
+    }
 
-                let fg = Color::Rgb(top_pixel[0], top_pixel[1], top_pixel[2]);
-                let bg = Color::Rgb(bottom_pixel[0], bottom_pixel[1], bottom_pixel[2]);
+    // Reads the previewed directory and its ignore rules again only once it's
+    // been modified, or always when sorted by the entries' metadata
+    fn dir_preview_entries(&mut self, dir_path: &Path) -> io::Result<Vec<SortEntry>> {
+        let modified = metadata(dir_path)?.modified()?;
+        let mode = self.sort.mode;
+
+        if let Some(preview) = &self.dir_preview
+            && !mode.reads_metadata()
+            && preview.path == dir_path
+            && preview.modified == modified
+            && preview.mode == mode
+            && preview.rules.is_some() == (self.ignore_mode != IgnoreMode::Off)
+        {
+            return Ok(preview.entries.clone());
+        }
 
-                buf.cell_mut((area.x + x, area.y + y))
-                    .unwrap()
-                    .set_style(Style::default().fg(fg).bg(bg))
-                    .set_symbol("▀");
-            }
+        let entries: Vec<SortEntry> = read_dir(dir_path)?
+            .filter_map(|entry_result| entry_result.ok())
+            .map(|entry| SortEntry::from_dir_entry(&entry, mode))
+            .collect();
+
+        self.dir_preview = Some(DirPreview {
+            path: dir_path.to_path_buf(),
+            modified,
+            mode,
+            entries: entries.clone(),
+            rules: self.ignore_rules_for(dir_path),
+        });
+
+        Ok(entries)
+    }
+
+    pub fn render_file_preview(
+        &mut self,
+        file_path: &Path,
+        block: Block,
+        area: Rect,
+        buf: &mut Buffer,
+    ) {
+        if let Some(kind) = ArchiveKind::from_path(file_path) {
+            self.render_archive_contents(file_path, kind, block, area, buf);
+            return;
+        }
+
+        if let Some(file_text) = self.text_preview(file_path) {
+            Paragraph::new(file_text).block(block).render(area, buf);
+            return;
+        }
+
+        let image_area = block.inner(area);
+        if self.render_image_preview(file_path, image_area, buf) {
+            block.render(area, buf);
+        }
+    }
+
+    // Reads the file again only once it's been modified
+    fn text_preview(&mut self, file_path: &Path) -> Option<String> {
+        let modified = metadata(file_path)
+            .and_then(|metadata| metadata.modified())
+            .ok()?;
+
+        if let Some(preview) = &self.text_preview
+            && preview.path == file_path
+            && preview.modified == modified
+        {
+            return preview.text.clone();
+        }
+
+        let text = read_text_start(file_path);
+        self.text_preview = Some(TextPreview {
+            path: file_path.to_path_buf(),
+            modified,
+            text: text.clone(),
+        });
+
+        text
+    }
+}
+
+// The first MAX_PREVIEW_BYTES of the file when they're UTF-8, a character
+// cut in half at the end doesn't count against it
+fn read_text_start(file_path: &Path) -> Option<String> {
+    let mut bytes = Vec::new();
+    File::open(file_path)
+        .ok()?
+        .take(MAX_PREVIEW_BYTES)
+        .read_to_end(&mut bytes)
+        .ok()?;
+
+    match String::from_utf8(bytes) {
+        Ok(text) => Some(text),
+        Err(e) if e.utf8_error().error_len().is_none() => {
+            let valid = e.utf8_error().valid_up_to();
+            let mut bytes = e.into_bytes();
+            bytes.truncate(valid);
+            String::from_utf8(bytes).ok()
         }
+        Err(_) => None,
     }
 }
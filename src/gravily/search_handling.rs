@@ -0,0 +1,180 @@
+use super::FileManager;
+use crate::gravily::{InputMode, Search};
+
+use ratatui::{
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+};
+use regex::{Regex, RegexBuilder};
+
+impl FileManager {
+    pub fn start_search(&mut self, backward: bool) {
+        let use_regex = self.search.as_ref().is_some_and(|search| search.use_regex);
+
+        self.search = Some(Search {
+            query: String::new(),
+            pattern: None,
+            backward,
+            use_regex,
+            origin: self.state.selected().unwrap_or(0),
+        });
+        self.input.reset();
+        self.input_mode = InputMode::Search;
+    }
+
+    pub fn toggle_search_regex(&mut self) {
+        if let Some(search) = &mut self.search {
+            search.use_regex = !search.use_regex;
+        }
+        self.update_search();
+    }
+
+    // Recompiles the pattern and moves to the first match at or after where
+    // the search started
+    pub fn update_search(&mut self) {
+        let Some(search) = &mut self.search else {
+            return;
+        };
+
+        search.query = self.input.value().to_string();
+        search.pattern = compile_pattern(&search.query, search.use_regex);
+
+        let origin = search.origin;
+        if search.pattern.is_some() {
+            if let Some(index) = self.find_match(origin, false) {
+                self.state.select(Some(index));
+            }
+        } else {
+            self.state.select(Some(origin));
+        }
+    }
+
+    // An empty search clears the highlighting
+    pub fn finish_search(&mut self) {
+        if self.input.value().is_empty() {
+            self.search = None;
+        }
+
+        self.input.reset();
+        self.input_mode = InputMode::Navigation;
+    }
+
+    pub fn cancel_search(&mut self) {
+        if let Some(search) = self.search.take() {
+            self.state.select(Some(search.origin));
+        }
+
+        self.input.reset();
+        self.input_mode = InputMode::Navigation;
+    }
+
+    // `reverse` goes against the direction the search was started in, like N
+    pub fn jump_to_match(&mut self, reverse: bool) {
+        let Some(current) = self.state.selected() else {
+            return;
+        };
+
+        let backward = self
+            .search
+            .as_ref()
+            .is_some_and(|search| search.backward != reverse);
+
+        let start = if backward {
+            current + self.path_items.len().max(1) - 1
+        } else {
+            current + 1
+        };
+
+        match self.find_match(start, reverse) {
+            Some(index) => self.state.select(Some(index)),
+            None if self.search.is_some() => {
+                self.error = format!("No matches for {}", self.search_description())
+            }
+            None => {}
+        }
+    }
+
+    // Walks every entry once from `start`, wrapping around the listing
+    fn find_match(&self, start: usize, reverse: bool) -> Option<usize> {
+        let search = self.search.as_ref()?;
+        let pattern = search.pattern.as_ref()?;
+        let len = self.path_items.len();
+
+        if len == 0 {
+            return None;
+        }
+
+        let backward = search.backward != reverse;
+
+        (0..len)
+            .map(|step| {
+                if backward {
+                    (start % len + len - step) % len
+                } else {
+                    (start + step) % len
+                }
+            })
+            .find(|&index| pattern.is_match(&self.path_items[index].to_string_lossy()))
+    }
+
+    pub fn search_description(&self) -> String {
+        match &self.search {
+            Some(search) if search.use_regex => format!("regex {:?}", search.query),
+            Some(search) => format!("{:?}", search.query),
+            None => String::new(),
+        }
+    }
+
+    // The file name with every match of the current search highlighted
+    pub fn highlight_matches(&self, file_name: String) -> Line<'static> {
+        let Some(pattern) = self
+            .search
+            .as_ref()
+            .and_then(|search| search.pattern.as_ref())
+        else {
+            return Line::from(file_name);
+        };
+
+        highlight_pattern(pattern, &file_name)
+    }
+}
+
+// Every match of `pattern` in black on yellow
+pub fn highlight_pattern(pattern: &Regex, text: &str) -> Line<'static> {
+    let mut spans = Vec::new();
+    let mut last_end = 0;
+
+    for found in pattern.find_iter(text).filter(|found| !found.is_empty()) {
+        spans.push(Span::from(text[last_end..found.start()].to_string()));
+        spans.push(
+            Span::from(found.as_str().to_string())
+                .style(Style::default().fg(Color::Black).bg(Color::Yellow))
+                .bold(),
+        );
+        last_end = found.end();
+    }
+
+    spans.push(Span::from(text[last_end..].to_string()));
+    Line::from(spans)
+}
+
+// Smart case: the search only cares about case once the query has an
+// uppercase letter in it. Plain queries are escaped so they match literally
+pub fn compile_pattern(query: &str, use_regex: bool) -> Option<Regex> {
+    if query.is_empty() {
+        return None;
+    }
+
+    let case_insensitive = !query.chars().any(char::is_uppercase);
+
+    let pattern = if use_regex {
+        query.to_string()
+    } else {
+        regex::escape(query)
+    };
+
+    RegexBuilder::new(&pattern)
+        .case_insensitive(case_insensitive)
+        .build()
+        .ok()
+}
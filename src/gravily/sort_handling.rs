@@ -0,0 +1,390 @@
+use super::FileManager;
+use crate::gravily::{Sort, SortEntry, SortMode};
+
+use std::cmp::Ordering;
+use std::ffi::{OsStr, OsString};
+use std::fs::{DirEntry, Metadata};
+use std::iter::Peekable;
+use std::path::Path;
+
+const SORT_MODES: [(&str, SortMode); 7] = [
+    ("name", SortMode::Name),
+    ("extension", SortMode::Extension),
+    ("size", SortMode::Size),
+    ("mtime", SortMode::Modified),
+    ("ctime", SortMode::Changed),
+    ("created", SortMode::Created),
+    ("type", SortMode::Type),
+];
+
+impl SortEntry {
+    pub fn from_dir_entry(entry: &DirEntry, mode: SortMode) -> Self {
+        let file_type = entry.file_type().ok();
+        let is_symlink = file_type.is_some_and(|file_type| file_type.is_symlink());
+
+        // symlinks are sorted with what they point to
+        let is_dir = if is_symlink {
+            entry.path().is_dir()
+        } else {
+            file_type.is_some_and(|file_type| file_type.is_dir())
+        };
+
+        let kind = match (is_dir, is_symlink) {
+            (true, _) => 0,
+            (false, true) => 1,
+            (false, false) if file_type.is_some_and(|file_type| file_type.is_file()) => 2,
+            _ => 3,
+        };
+
+        let metadata = if mode.reads_metadata() {
+            entry.metadata().ok()
+        } else {
+            None
+        };
+
+        Self {
+            name: entry.file_name(),
+            is_dir,
+            kind,
+            size: metadata.as_ref().map_or(0, |metadata| metadata.len()),
+            modified: metadata
+                .as_ref()
+                .and_then(|metadata| metadata.modified().ok()),
+            created: metadata
+                .as_ref()
+                .and_then(|metadata| metadata.created().ok()),
+            changed: metadata.as_ref().and_then(changed_time),
+        }
+    }
+
+    // For entries found by a walk, which already read their metadata
+    pub fn from_metadata(name: OsString, path: &Path, metadata: &Metadata) -> Self {
+        let is_symlink = metadata.is_symlink();
+
+        let is_dir = if is_symlink {
+            path.is_dir()
+        } else {
+            metadata.is_dir()
+        };
+
+        let kind = match (is_dir, is_symlink) {
+            (true, _) => 0,
+            (false, true) => 1,
+            (false, false) if metadata.is_file() => 2,
+            _ => 3,
+        };
+
+        Self {
+            name,
+            is_dir,
+            kind,
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+            created: metadata.created().ok(),
+            changed: changed_time(metadata),
+        }
+    }
+
+    pub fn from_name(name: OsString, is_dir: bool) -> Self {
+        Self {
+            name,
+            is_dir,
+            kind: if is_dir { 0 } else { 2 },
+            size: 0,
+            modified: None,
+            created: None,
+            changed: None,
+        }
+    }
+}
+
+impl Default for Sort {
+    fn default() -> Self {
+        Self {
+            mode: SortMode::Name,
+            reverse: false,
+            case_sensitive: false,
+            directories_first: true,
+        }
+    }
+}
+
+impl SortMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = match name {
+            "natural" => "name",
+            "ext" => "extension",
+            "modified" => "mtime",
+            _ => name,
+        };
+
+        SORT_MODES
+            .into_iter()
+            .find(|(mode_name, _)| *mode_name == name)
+            .map(|(_, mode)| mode)
+    }
+
+    pub fn name(self) -> &'static str {
+        SORT_MODES
+            .into_iter()
+            .find(|(_, mode)| *mode == self)
+            .map_or("", |(name, _)| name)
+    }
+
+    // Whether entries have to be stat'ed to be compared
+    pub fn reads_metadata(self) -> bool {
+        matches!(
+            self,
+            SortMode::Size | SortMode::Modified | SortMode::Created | SortMode::Changed
+        )
+    }
+
+    fn next(self) -> Self {
+        let index = SORT_MODES
+            .iter()
+            .position(|(_, mode)| *mode == self)
+            .unwrap_or(0);
+
+        SORT_MODES[(index + 1) % SORT_MODES.len()].1
+    }
+}
+
+impl Sort {
+    pub fn description(&self) -> String {
+        let mut description = String::from(self.mode.name());
+
+        if self.reverse {
+            description.push_str(" reversed");
+        }
+        if self.case_sensitive {
+            description.push_str(", case sensitive");
+        }
+        if !self.directories_first {
+            description.push_str(", mixed");
+        }
+
+        description
+    }
+
+    pub fn sort(&self, entries: &mut [SortEntry]) {
+        entries.sort_by(|a, b| self.compare(a, b));
+    }
+
+    fn compare(&self, a: &SortEntry, b: &SortEntry) -> Ordering {
+        // directories stay on top whichever way the rest is sorted
+        if self.directories_first && a.is_dir != b.is_dir {
+            return b.is_dir.cmp(&a.is_dir);
+        }
+
+        let by_name = || self.compare_names(&a.name, &b.name);
+
+        let ordering = match self.mode {
+            SortMode::Name => by_name(),
+            SortMode::Extension => self
+                .compare_names(
+                    Path::new(&a.name).extension().unwrap_or_default(),
+                    Path::new(&b.name).extension().unwrap_or_default(),
+                )
+                .then_with(by_name),
+            SortMode::Size => a.size.cmp(&b.size).then_with(by_name),
+            SortMode::Modified => a.modified.cmp(&b.modified).then_with(by_name),
+            SortMode::Created => a.created.cmp(&b.created).then_with(by_name),
+            SortMode::Changed => a.changed.cmp(&b.changed).then_with(by_name),
+            SortMode::Type => a.kind.cmp(&b.kind).then_with(by_name),
+        };
+
+        if self.reverse {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+
+    // Runs for every pair on every reload, so case is folded a character at
+    // a time rather than into new strings
+    fn compare_names(&self, a: &OsStr, b: &OsStr) -> Ordering {
+        let (a, b) = (a.to_string_lossy(), b.to_string_lossy());
+
+        if self.case_sensitive {
+            natural_cmp(&a, &b)
+        } else {
+            compare_chars(
+                a.chars().flat_map(char::to_lowercase),
+                b.chars().flat_map(char::to_lowercase),
+            )
+            .then_with(|| natural_cmp(&a, &b))
+        }
+    }
+}
+
+impl FileManager {
+    pub fn sort_description(&self) -> String {
+        self.sort.description()
+    }
+
+    pub fn cycle_sort_mode(&mut self) {
+        let mode = self.sort.mode.next();
+        self.reload_keeping_hovered(|file_manager| file_manager.sort.mode = mode);
+    }
+
+    pub fn toggle_sort_reverse(&mut self) {
+        self.reload_keeping_hovered(|file_manager| file_manager.sort.reverse ^= true);
+    }
+
+    pub fn toggle_sort_case(&mut self) {
+        self.reload_keeping_hovered(|file_manager| file_manager.sort.case_sensitive ^= true);
+    }
+
+    pub fn toggle_directories_first(&mut self) {
+        self.reload_keeping_hovered(|file_manager| file_manager.sort.directories_first ^= true);
+    }
+
+    // Accepts "name|ext|size|mtime|ctime|created|type", optionally followed by
+    // "reverse", and the "reverse", "case" and "dirs" toggles on their own
+    pub fn apply_sort_command(&mut self, arguments: &[&str]) -> Result<(), String> {
+        let mut sort = self.sort;
+
+        match arguments {
+            [] => {
+                return Err(String::from(
+                    "Usage: sort name|ext|size|mtime|ctime|created|type [reverse]",
+                ));
+            }
+            ["reverse"] => sort.reverse = !sort.reverse,
+            ["case"] => sort.case_sensitive = !sort.case_sensitive,
+            ["dirs"] => sort.directories_first = !sort.directories_first,
+            [mode, rest @ ..] => {
+                sort.mode = SortMode::from_name(mode)
+                    .ok_or_else(|| format!("Unknown sort mode {:?}", mode))?;
+
+                sort.reverse = match rest {
+                    [] => false,
+                    ["reverse" | "rev" | "desc"] => true,
+                    _ => return Err(format!("Unexpected {:?} after sort mode", rest.join(" "))),
+                };
+            }
+        }
+
+        self.reload_keeping_hovered(|file_manager| file_manager.sort = sort);
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn changed_time(metadata: &Metadata) -> Option<(i64, i64)> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some((metadata.ctime(), metadata.ctime_nsec()))
+}
+
+#[cfg(not(unix))]
+fn changed_time(_metadata: &Metadata) -> Option<(i64, i64)> {
+    None
+}
+
+// Compares runs of digits by their value, so "file2" comes before "file10"
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    compare_chars(a.chars(), b.chars())
+}
+
+fn compare_chars(a: impl Iterator<Item = char>, b: impl Iterator<Item = char>) -> Ordering {
+    let mut a_chars = a.peekable();
+    let mut b_chars = b.peekable();
+
+    loop {
+        match (a_chars.peek(), b_chars.peek()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a_char), Some(b_char)) if a_char.is_ascii_digit() && b_char.is_ascii_digit() => {
+                let ordering = compare_numbers(&mut a_chars, &mut b_chars);
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(a_char), Some(b_char)) => {
+                let ordering = a_char.cmp(b_char);
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+
+                a_chars.next();
+                b_chars.next();
+            }
+        }
+    }
+}
+
+// Consumes a run of digits from both sides. Longer runs are bigger once
+// leading zeros are ignored, and equal values with more zeros come after
+fn compare_numbers<A, B>(a: &mut Peekable<A>, b: &mut Peekable<B>) -> Ordering
+where
+    A: Iterator<Item = char>,
+    B: Iterator<Item = char>,
+{
+    let a_zeros = skip_zeros(a);
+    let b_zeros = skip_zeros(b);
+
+    // the first differing digit decides between runs of the same length
+    let mut first_difference = Ordering::Equal;
+    loop {
+        match (
+            a.next_if(char::is_ascii_digit),
+            b.next_if(char::is_ascii_digit),
+        ) {
+            (Some(a_digit), Some(b_digit)) => {
+                first_difference = first_difference.then(a_digit.cmp(&b_digit));
+            }
+            (None, None) => break,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+        }
+    }
+
+    first_difference.then(a_zeros.cmp(&b_zeros))
+}
+
+fn skip_zeros(chars: &mut Peekable<impl Iterator<Item = char>>) -> usize {
+    let mut zeros = 0;
+
+    while chars.next_if_eq(&'0').is_some() {
+        zeros += 1;
+    }
+
+    zeros
+}
+
+#[cfg(test)]
+mod tests {
+    use super::natural_cmp;
+
+    use std::cmp::Ordering;
+
+    #[test]
+    fn orders_numbers_by_value() {
+        assert_eq!(natural_cmp("file2", "file10"), Ordering::Less);
+        assert_eq!(natural_cmp("file10", "file9"), Ordering::Greater);
+        assert_eq!(natural_cmp("v1.10.2", "v1.9.12"), Ordering::Greater);
+    }
+
+    #[test]
+    fn puts_more_leading_zeros_after_the_same_value() {
+        assert_eq!(natural_cmp("file01", "file1"), Ordering::Greater);
+        assert_eq!(natural_cmp("file001", "file2"), Ordering::Less);
+        assert_eq!(natural_cmp("file007", "file007"), Ordering::Equal);
+    }
+
+    #[test]
+    fn compares_text_character_by_character() {
+        assert_eq!(natural_cmp("abc", "abd"), Ordering::Less);
+        assert_eq!(natural_cmp("ab", "abc"), Ordering::Less);
+        assert_eq!(natural_cmp("B", "a"), Ordering::Less);
+        assert_eq!(natural_cmp("", ""), Ordering::Equal);
+    }
+
+    #[test]
+    fn compares_digits_before_letters_like_plain_text() {
+        assert_eq!(natural_cmp("a1", "ab"), Ordering::Less);
+        assert_eq!(natural_cmp("10a", "10"), Ordering::Greater);
+    }
+}
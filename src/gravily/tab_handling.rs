@@ -0,0 +1,180 @@
+use super::FileManager;
+use crate::gravily::Tab;
+
+use ratatui::{style::Stylize, text::Line};
+use tui_input::Input;
+
+use std::mem;
+use std::path::PathBuf;
+
+impl FileManager {
+    // Moves the shown tab's state out, leaving the fields empty for another
+    fn stash_tab(&mut self) -> Tab {
+        Tab {
+            path: mem::take(&mut self.path),
+            archive: self.archive.take(),
+            state: mem::take(&mut self.state),
+            back_history: mem::take(&mut self.back_history),
+            forward_history: mem::take(&mut self.forward_history),
+            last_selected: mem::take(&mut self.last_selected),
+            filters: mem::take(&mut self.filters),
+            sort: self.sort,
+            find_results: self.find_results.take(),
+        }
+    }
+
+    fn show_tab(&mut self, tab: Tab) {
+        self.path = tab.path;
+        self.archive = tab.archive;
+        self.state = tab.state;
+        self.back_history = tab.back_history;
+        self.forward_history = tab.forward_history;
+        self.last_selected = tab.last_selected;
+        self.filters = tab.filters;
+        self.sort = tab.sort;
+        self.find_results = tab.find_results;
+
+        self.search = None;
+        self.read_path_items();
+    }
+
+    fn tab_count(&self) -> usize {
+        self.tabs.len().max(1)
+    }
+
+    // The shown tab's slot in `tabs` only holds a placeholder
+    fn tab_path(&self, index: usize) -> Option<&PathBuf> {
+        if index == self.active_tab {
+            return Some(&self.path);
+        }
+
+        self.tabs.get(index).map(|tab| &tab.path)
+    }
+
+    // Opens a tab on the current directory right after this one
+    pub fn new_tab(&mut self) {
+        if self.tabs.is_empty() {
+            self.tabs.push(Tab::default());
+        }
+
+        let path = match &self.archive {
+            Some(archive) => archive
+                .archive_path
+                .parent()
+                .map_or_else(|| self.path.clone(), |parent| parent.to_path_buf()),
+            None => self.path.clone(),
+        };
+        let sort = self.sort;
+
+        let current = self.stash_tab();
+        self.tabs[self.active_tab] = current;
+
+        self.active_tab += 1;
+        self.tabs.insert(self.active_tab, Tab::default());
+
+        self.sort = sort;
+        self.path = path;
+        self.read_path_items();
+        self.state.select_first();
+    }
+
+    pub fn close_tab(&mut self) {
+        if self.tab_count() == 1 {
+            self.error = String::from("Can't close the last tab, q quits");
+            return;
+        }
+
+        self.tabs.remove(self.active_tab);
+        self.active_tab = self.active_tab.min(self.tabs.len() - 1);
+
+        let tab = mem::take(&mut self.tabs[self.active_tab]);
+        self.show_tab(tab);
+    }
+
+    pub fn switch_tab(&mut self, index: usize) {
+        if index >= self.tab_count() {
+            self.error = format!("There's no tab {}", index + 1);
+            return;
+        }
+        if index == self.active_tab {
+            return;
+        }
+
+        let current = self.stash_tab();
+        self.tabs[self.active_tab] = current;
+        self.active_tab = index;
+
+        let tab = mem::take(&mut self.tabs[index]);
+        self.show_tab(tab);
+    }
+
+    // Wraps around at either end
+    pub fn cycle_tab(&mut self, backward: bool) {
+        let count = self.tab_count();
+        let index = if backward {
+            (self.active_tab + count - 1) % count
+        } else {
+            (self.active_tab + 1) % count
+        };
+
+        self.switch_tab(index);
+    }
+
+    pub fn move_tab(&mut self, backward: bool) {
+        let count = self.tab_count();
+        let target = if backward {
+            self.active_tab.checked_sub(1)
+        } else {
+            Some(self.active_tab + 1).filter(|&target| target < count)
+        };
+
+        if let Some(target) = target {
+            self.tabs.swap(self.active_tab, target);
+            self.active_tab = target;
+        }
+    }
+
+    // Fills the copy/move destination with the directory tab `index` shows
+    pub fn fill_tab_destination(&mut self, index: usize) {
+        if index == self.active_tab || index >= self.tab_count() {
+            return;
+        }
+
+        if self.tabs[index].archive.is_some() {
+            self.error = format!("Tab {} is inside an archive", index + 1);
+            return;
+        }
+
+        let mut destination = self.tabs[index].path.display().to_string();
+        if !destination.ends_with(std::path::MAIN_SEPARATOR) {
+            destination.push(std::path::MAIN_SEPARATOR);
+        }
+        self.input = Input::new(destination);
+    }
+
+    // Only shown once there's more than one tab
+    pub fn tab_bar(&self) -> Option<Line<'static>> {
+        if self.tab_count() < 2 {
+            return None;
+        }
+
+        let mut spans = Vec::new();
+        for index in 0..self.tab_count() {
+            let name = self.tab_path(index).map_or(String::new(), |path| {
+                path.file_name().map_or_else(
+                    || path.display().to_string(),
+                    |name| name.to_string_lossy().to_string(),
+                )
+            });
+            let label = format!(" {}:{} ", index + 1, name);
+
+            if index == self.active_tab {
+                spans.push(label.black().on_blue().bold());
+            } else {
+                spans.push(label.into());
+            }
+        }
+
+        Some(Line::from(spans))
+    }
+}
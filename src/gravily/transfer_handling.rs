@@ -0,0 +1,125 @@
+use super::FileManager;
+use crate::gravily::JobUpdate;
+
+use std::fs::{copy, create_dir, read_dir, remove_dir_all, remove_file, rename, set_permissions};
+use std::io;
+use std::path::Path;
+
+impl FileManager {
+    // Prefills the destination with the current directory
+    pub fn destination_input(&self) -> String {
+        let mut destination = self.path.display().to_string();
+        if !destination.ends_with(std::path::MAIN_SEPARATOR) {
+            destination.push(std::path::MAIN_SEPARATOR);
+        }
+
+        destination
+    }
+
+    // Copies or moves the marked (or hovered) items into the directory named
+    // by the input. Nothing that already exists there gets overwritten
+    pub fn transfer_selection(&mut self, keep_originals: bool) {
+        self.error = String::new();
+
+        let verb = if keep_originals { "copying" } else { "moving" };
+        let destination = self.path.join(self.input.value_and_reset().trim());
+
+        if !destination.is_dir() {
+            self.error = format!(
+                "Error {} to {}: Not a directory",
+                verb,
+                destination.display()
+            );
+            return;
+        }
+
+        let paths = self.selected_paths();
+        if paths.is_empty() {
+            self.error = format!("Error {}: Nothing selected", verb);
+            return;
+        }
+
+        // moved items don't exist where they were marked anymore
+        if !keep_originals {
+            self.marked.clear();
+        }
+
+        let description = if keep_originals {
+            format!("Copying to {}", destination.display())
+        } else {
+            format!("Moving to {}", destination.display())
+        };
+
+        self.spawn_job(description, paths.len(), move |updates| {
+            for path in paths {
+                let result = transfer(&path, &destination, keep_originals)
+                    .map_err(|e| format!("{}: {}", path.display(), e));
+                let _ = updates.send(JobUpdate::Done(result));
+            }
+        });
+    }
+}
+
+fn transfer(path: &Path, destination: &Path, keep_original: bool) -> io::Result<()> {
+    let Some(file_name) = path.file_name() else {
+        return Err(io::Error::other("Can't copy the root directory"));
+    };
+
+    let target = destination.join(file_name);
+    if target.symlink_metadata().is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", target.display()),
+        ));
+    }
+
+    // a directory can't go inside itself
+    if let (Ok(path), Ok(destination)) = (path.canonicalize(), destination.canonicalize())
+        && destination.starts_with(&path)
+    {
+        return Err(io::Error::other("Can't put a directory inside itself"));
+    }
+
+    if !keep_original {
+        // only moves across filesystems have to copy
+        match rename(path, &target) {
+            Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {}
+            result => return result,
+        }
+    }
+
+    copy_recursively(path, &target)?;
+
+    if !keep_original {
+        if path.symlink_metadata()?.is_dir() {
+            remove_dir_all(path)?;
+        } else {
+            remove_file(path)?;
+        }
+    }
+
+    Ok(())
+}
+
+// Symlinks are copied as links rather than followed
+fn copy_recursively(from: &Path, to: &Path) -> io::Result<()> {
+    let metadata = from.symlink_metadata()?;
+
+    if metadata.is_symlink() {
+        #[cfg(unix)]
+        return std::os::unix::fs::symlink(std::fs::read_link(from)?, to);
+    }
+
+    if metadata.is_dir() {
+        create_dir(to)?;
+
+        for entry in read_dir(from)? {
+            let entry = entry?;
+            copy_recursively(&entry.path(), &to.join(entry.file_name()))?;
+        }
+
+        return set_permissions(to, metadata.permissions());
+    }
+
+    copy(from, to).map(|_| ())
+}
@@ -0,0 +1,271 @@
+use super::FileManager;
+use crate::gravily::JobUpdate;
+use crate::gravily::image_handling::open_oriented;
+
+use image::{
+    DynamicImage, ImageFormat,
+    codecs::{jpeg::JpegEncoder, webp::WebPEncoder},
+    imageops::FilterType,
+};
+
+use std::fs::{File, remove_file};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+const DEFAULT_QUALITY: u8 = 90;
+
+// Largest image a transform may produce, bigger ones would take gigabytes
+const MAX_OUTPUT_PIXELS: u64 = 100_000_000;
+
+#[derive(Debug, Clone, Copy)]
+enum ImageTransform {
+    Rotate(u16),
+    FlipHorizontal,
+    FlipVertical,
+    Scale(u32),
+    Resize(u32, u32),
+    StripMetadata,
+    Convert(ImageFormat, u8),
+}
+
+impl FileManager {
+    pub fn transform_images(&mut self) {
+        self.error = String::new();
+
+        let command = self.input.value_and_reset();
+        let transform = match parse_transform(&command) {
+            Ok(transform) => transform,
+            Err(e) => {
+                self.error = e;
+                return;
+            }
+        };
+
+        let images: Vec<PathBuf> = self
+            .selected_paths()
+            .into_iter()
+            .filter(|path| ImageFormat::from_path(path).is_ok())
+            .collect();
+
+        if images.is_empty() {
+            self.error = String::from("Error transforming images: No images selected");
+            return;
+        }
+
+        let pattern = self.config.image_name_pattern.clone();
+
+        self.spawn_job(
+            format!("Transforming images ({})", command.trim()),
+            images.len(),
+            move |updates| {
+                for image in images {
+                    let result = apply_transform(&image, transform, &pattern)
+                        .map_err(|e| format!("{}: {}", image.display(), e));
+
+                    if updates.send(JobUpdate::Done(result)).is_err() {
+                        return;
+                    }
+                }
+            },
+        );
+    }
+}
+
+pub fn is_valid_transform(command: &str) -> Result<(), String> {
+    parse_transform(command).map(|_| ())
+}
+
+// Accepts "rotate 90|180|270", "flip h|v", "resize 50%", "resize 800x600",
+// "strip" and "convert png|jpg|webp|bmp|tiff", with a quality after jpg
+fn parse_transform(command: &str) -> Result<ImageTransform, String> {
+    let words: Vec<&str> = command.split_whitespace().collect();
+
+    let transform = match words.as_slice() {
+        ["rotate", degrees] => match *degrees {
+            "90" | "-270" => ImageTransform::Rotate(90),
+            "180" | "-180" => ImageTransform::Rotate(180),
+            "270" | "-90" => ImageTransform::Rotate(270),
+            _ => {
+                return Err(format!(
+                    "Can only rotate by 90, 180 or 270 degrees, not {}",
+                    degrees
+                ));
+            }
+        },
+
+        ["flip", "h" | "horizontal"] => ImageTransform::FlipHorizontal,
+        ["flip", "v" | "vertical"] => ImageTransform::FlipVertical,
+
+        ["resize", size] => {
+            if let Some(percentage) = size.strip_suffix('%') {
+                match percentage.parse() {
+                    Ok(percentage) if percentage > 0 => ImageTransform::Scale(percentage),
+                    _ => return Err(format!("Invalid percentage {:?}", size)),
+                }
+            } else {
+                match size.split_once('x').map(|(w, h)| (w.parse(), h.parse())) {
+                    Some((Ok(width), Ok(height))) if width > 0 && height > 0 => {
+                        ImageTransform::Resize(width, height)
+                    }
+                    _ => return Err(format!("Invalid size {:?}, expected WIDTHxHEIGHT", size)),
+                }
+            }
+        }
+
+        ["strip"] => ImageTransform::StripMetadata,
+
+        ["convert", format, rest @ ..] if rest.len() <= 1 => {
+            let format = match format.to_lowercase().as_str() {
+                "png" => ImageFormat::Png,
+                "jpg" | "jpeg" => ImageFormat::Jpeg,
+                "webp" => ImageFormat::WebP,
+                "bmp" => ImageFormat::Bmp,
+                "tif" | "tiff" => ImageFormat::Tiff,
+                _ => return Err(format!("Can't convert to {:?}", format)),
+            };
+
+            // only the JPEG encoder is lossy, WebP is written lossless
+            let quality = match rest.first().map(|quality| quality.parse::<u8>()) {
+                None => DEFAULT_QUALITY,
+                Some(_) if format != ImageFormat::Jpeg => {
+                    return Err(format!(
+                        "Only jpg takes a quality, {} is written lossless",
+                        words[1]
+                    ));
+                }
+                Some(Ok(quality)) if (1..=100).contains(&quality) => quality,
+                Some(_) => return Err(String::from("Quality must be between 1 and 100")),
+            };
+
+            ImageTransform::Convert(format, quality)
+        }
+
+        _ => return Err(format!("Unknown image action {:?}", command.trim())),
+    };
+
+    Ok(transform)
+}
+
+fn apply_transform(path: &Path, transform: ImageTransform, pattern: &str) -> Result<(), String> {
+    let img = open_oriented(path).map_err(|e| e.to_string())?;
+    let source_format = ImageFormat::from_path(path).map_err(|e| e.to_string())?;
+
+    let (img, format, quality, action) = match transform {
+        ImageTransform::Rotate(90) => (img.rotate90(), source_format, DEFAULT_QUALITY, "rotated90"),
+        ImageTransform::Rotate(180) => (
+            img.rotate180(),
+            source_format,
+            DEFAULT_QUALITY,
+            "rotated180",
+        ),
+        ImageTransform::Rotate(_) => (
+            img.rotate270(),
+            source_format,
+            DEFAULT_QUALITY,
+            "rotated270",
+        ),
+        ImageTransform::FlipHorizontal => (img.fliph(), source_format, DEFAULT_QUALITY, "flipped"),
+        ImageTransform::FlipVertical => (img.flipv(), source_format, DEFAULT_QUALITY, "flipped"),
+        ImageTransform::Scale(percentage) => {
+            let scale = |side: u32| (u64::from(side) * u64::from(percentage) / 100).max(1);
+            let (width, height) = (scale(img.width()), scale(img.height()));
+            check_output_size(width, height)?;
+
+            // both fit in a u32 once they're under MAX_OUTPUT_PIXELS
+            let (width, height) = (width as u32, height as u32);
+            (
+                img.resize_exact(width, height, FilterType::Lanczos3),
+                source_format,
+                DEFAULT_QUALITY,
+                "resized",
+            )
+        }
+        ImageTransform::Resize(width, height) => {
+            // the image is fitted inside the box, keeping its aspect ratio
+            let ratio = (f64::from(width) / f64::from(img.width()))
+                .min(f64::from(height) / f64::from(img.height()));
+            check_output_size(
+                (f64::from(img.width()) * ratio) as u64,
+                (f64::from(img.height()) * ratio) as u64,
+            )?;
+
+            (
+                img.resize(width, height, FilterType::Lanczos3),
+                source_format,
+                DEFAULT_QUALITY,
+                "resized",
+            )
+        }
+        // the encoders don't write metadata back, so re-encoding is enough
+        ImageTransform::StripMetadata => (img, source_format, DEFAULT_QUALITY, "stripped"),
+        ImageTransform::Convert(format, quality) => (img, format, quality, "converted"),
+    };
+
+    let output = output_path(path, pattern, action, format);
+    save_image(&img, &output, format, quality)
+}
+
+fn check_output_size(width: u64, height: u64) -> Result<(), String> {
+    if width.saturating_mul(height) > MAX_OUTPUT_PIXELS {
+        return Err(format!(
+            "{}x{} is too large, the limit is {} megapixels",
+            width,
+            height,
+            MAX_OUTPUT_PIXELS / 1_000_000
+        ));
+    }
+
+    Ok(())
+}
+
+// Fills in {name}, {action} and {ext} of the naming pattern, relative to the
+// original's directory
+fn output_path(path: &Path, pattern: &str, action: &str, format: ImageFormat) -> PathBuf {
+    let name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let extension = match path.extension() {
+        Some(extension) if ImageFormat::from_extension(extension) == Some(format) => {
+            extension.to_string_lossy().to_string()
+        }
+        _ => format.extensions_str()[0].to_string(),
+    };
+
+    let file_name = pattern
+        .replace("{name}", &name)
+        .replace("{action}", action)
+        .replace("{ext}", &extension);
+
+    path.with_file_name(file_name)
+}
+
+fn save_image(
+    img: &DynamicImage,
+    output: &Path,
+    format: ImageFormat,
+    quality: u8,
+) -> Result<(), String> {
+    let file = File::create_new(output).map_err(|e| format!("{}: {}", output.display(), e))?;
+    let mut writer = BufWriter::new(file);
+
+    let result = match format {
+        ImageFormat::Jpeg => {
+            let encoder = JpegEncoder::new_with_quality(&mut writer, quality);
+            DynamicImage::ImageRgb8(img.to_rgb8()).write_with_encoder(encoder)
+        }
+        // the WebP encoder is lossless only, so the quality doesn't apply
+        ImageFormat::WebP => DynamicImage::ImageRgba8(img.to_rgba8())
+            .write_with_encoder(WebPEncoder::new_lossless(&mut writer)),
+        ImageFormat::Bmp => DynamicImage::ImageRgba8(img.to_rgba8()).write_to(&mut writer, format),
+        _ => img.write_to(&mut writer, format),
+    };
+
+    if let Err(e) = result {
+        let _ = remove_file(output);
+        return Err(e.to_string());
+    }
+
+    Ok(())
+}
@@ -1,15 +1,31 @@
+use std::env::args_os;
 use std::io;
+use std::process::exit;
 
 mod gravily;
 mod ui;
 
-use gravily::FileManager;
+use gravily::{FileManager, Options};
 
 fn main() -> io::Result<()> {
+    let options = match Options::parse(args_os().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => return Ok(()),
+        Err(e) => {
+            eprintln!("gravily: {}", e);
+            eprintln!("Try 'gravily --help' for more information.");
+            exit(2);
+        }
+    };
+
+    let mut file_manager = FileManager::new(options);
+    file_manager.set_up_chooser_output()?;
+
     let mut terminal = ratatui::init();
-    let result = FileManager::default().run(&mut terminal);
+    let result = file_manager.run(&mut terminal);
 
     ratatui::restore();
 
-    result
+    result?;
+    file_manager.write_results()
 }
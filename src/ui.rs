@@ -21,18 +21,52 @@ impl Widget for &mut FileManager {
             " Quit ".into(),
             "<q or esc> ".blue().bold(),
         ]);
-        let main_block = Block::bordered()
+        let mut main_block = Block::bordered()
             .title(title.centered())
             .title_bottom(instructions.centered())
             .border_set(border::PLAIN);
 
+        if let Some(tab_bar) = self.tab_bar() {
+            main_block = main_block.title(tab_bar.left_aligned());
+        }
+
+        if let Some(banner) = self.chooser_banner() {
+            main_block = main_block.title(banner.left_aligned());
+        }
+
+        if let Some(job_status) = self.job_status() {
+            main_block = main_block.title_bottom(job_status.right_aligned());
+        }
+
         let inner_area = Layout::default()
             .direction(ratatui::layout::Direction::Horizontal)
             .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
             .split(main_block.inner(area));
 
+        let full_area = main_block.inner(area);
+
         main_block.render(area, buf);
 
+        if self.is_bookmark_picker_open() {
+            self.render_bookmark_picker(full_area, buf);
+            return;
+        }
+
+        if self.is_finder_open() {
+            self.render_finder(full_area, buf);
+            return;
+        }
+
+        if self.is_grep_open() {
+            self.render_grep(full_area, buf);
+            return;
+        }
+
+        if self.is_gallery_open() {
+            self.render_gallery(full_area, buf);
+            return;
+        }
+
         self.render_file_items(inner_area[0], buf);
         self.render_peekable_items(inner_area[1], buf);
     }